@@ -0,0 +1,458 @@
+use std::fs;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use biot_savart::{Backend, Convention, Singularity, Solver, Units, DEFAULT_THETA};
+
+// Files the current density is read from
+pub enum Input {
+    // Three Gaussian cube files, in atomic units
+    Cubes([PathBuf; 3]),
+    // Three 3D .npy files of J and three 1D .npy files of the coordinates
+    Npy { j: [PathBuf; 3], cor: [PathBuf; 3] },
+    // .npz archive of jx, jy, jz, x_cor, y_cor and z_cor
+    Npz(PathBuf),
+    // Output of GIMIC, jvec.txt or jvec.vti, in atomic units
+    Gimic(PathBuf),
+}
+
+// Targets B is calculated at
+pub enum Targets {
+    // Rectilinear grid, where a dimension that is not given is the one of J
+    Grid([Option<Vec<f64>>; 3]),
+    // Nx3 array of points in a .npy file
+    Points(PathBuf),
+}
+
+// File format of B on a grid
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Format {
+    Cube,
+    Vtr,
+    Vti,
+    Npz,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "cube" => Ok(Format::Cube),
+            "vtr" => Ok(Format::Vtr),
+            "vti" => Ok(Format::Vti),
+            "npz" => Ok(Format::Npz),
+            _ => Err(format!(
+                "unknown format '{}', expected 'cube', 'vtr', 'vti' or 'npz'",
+                s
+            )),
+        }
+    }
+}
+
+pub struct Config {
+    pub input: Input,
+    pub units: Units,
+    pub convention: Convention,
+    pub center: [f64; 3],
+    pub solver: Solver,
+    pub singularity: Singularity,
+    pub backend: Backend,
+    pub targets: Targets,
+    pub output: PathBuf,
+    pub formats: Vec<Format>,
+}
+
+// Settings as given, before they are checked
+#[derive(Default)]
+struct Settings {
+    jx: Option<String>,
+    jy: Option<String>,
+    jz: Option<String>,
+    x_cor: Option<String>,
+    y_cor: Option<String>,
+    z_cor: Option<String>,
+    npz: Option<String>,
+    gimic: Option<String>,
+    units: Option<Units>,
+    convention: Option<Convention>,
+    center: Option<[f64; 3]>,
+    solver: Option<Solver>,
+    theta: Option<f64>,
+    singularity: Option<String>,
+    softening: Option<f64>,
+    backend: Option<Backend>,
+    target: [Option<Vec<f64>>; 3],
+    points: Option<String>,
+    output: Option<String>,
+    formats: Option<Vec<Format>>,
+}
+
+impl Config {
+    // Reads the settings of a configuration file, `key = value` per line with
+    // `#` starting comments, followed by `overrides` in the same form.
+    pub fn read(path: Option<&str>, overrides: &[String]) -> Result<Config, String> {
+        let mut settings = Settings::default();
+        if let Some(path) = path {
+            let contents = fs::read_to_string(path).map_err(|err| format!("{}: {}", path, err))?;
+            for (n, line) in contents.lines().enumerate() {
+                settings
+                    .set(line)
+                    .map_err(|err| format!("{}, line {}: {}", path, n + 1, err))?;
+            }
+        }
+        for line in overrides {
+            settings.set(line)?;
+        }
+        settings.check()
+    }
+}
+
+impl Settings {
+    fn set(&mut self, line: &str) -> Result<(), String> {
+        let line = line.split('#').next().unwrap().trim();
+        if line.is_empty() {
+            return Ok(());
+        }
+        let mut parts = line.splitn(2, '=');
+        let key = parts.next().unwrap().trim();
+        let value = parts
+            .next()
+            .map(str::trim)
+            .ok_or_else(|| format!("expected 'key = value', found '{}'", line))?;
+
+        match key {
+            "jx" => self.jx = Some(value.to_string()),
+            "jy" => self.jy = Some(value.to_string()),
+            "jz" => self.jz = Some(value.to_string()),
+            "x_cor" => self.x_cor = Some(value.to_string()),
+            "y_cor" => self.y_cor = Some(value.to_string()),
+            "z_cor" => self.z_cor = Some(value.to_string()),
+            "npz" => self.npz = Some(value.to_string()),
+            "gimic" => self.gimic = Some(value.to_string()),
+            "units" => self.units = Some(value.parse()?),
+            "convention" => self.convention = Some(value.parse()?),
+            "center" => {
+                let center = numbers(key, value)?;
+                if center.len() != 3 {
+                    return Err("center needs 3 coordinates".to_string());
+                }
+                self.center = Some([center[0], center[1], center[2]]);
+            }
+            "solver" => self.solver = Some(value.parse()?),
+            "theta" => self.theta = Some(number(key, value)?),
+            "singularity" => self.singularity = Some(value.to_string()),
+            "softening" => self.softening = Some(number(key, value)?),
+            "backend" => self.backend = Some(value.parse()?),
+            "target_x" => self.target[0] = Some(range(key, value)?),
+            "target_y" => self.target[1] = Some(range(key, value)?),
+            "target_z" => self.target[2] = Some(range(key, value)?),
+            "points" => self.points = Some(value.to_string()),
+            "output" => self.output = Some(value.to_string()),
+            "format" => {
+                let formats: Result<Vec<Format>, String> = value
+                    .split(|c: char| c == ',' || c.is_whitespace())
+                    .filter(|format| !format.is_empty())
+                    .map(str::parse)
+                    .collect();
+                self.formats = Some(formats?);
+            }
+            _ => return Err(format!("unknown setting '{}'", key)),
+        }
+        Ok(())
+    }
+
+    fn check(self) -> Result<Config, String> {
+        let file = match (self.npz, self.gimic) {
+            (Some(npz), None) => Some(Input::Npz(npz.into())),
+            (None, Some(gimic)) => Some(Input::Gimic(gimic.into())),
+            (None, None) => None,
+            _ => return Err("npz and gimic exclude each other".to_string()),
+        };
+        let input = match (file, self.jx, self.jy, self.jz) {
+            (Some(input), None, None, None) => {
+                if self.x_cor.is_some() || self.y_cor.is_some() || self.z_cor.is_some() {
+                    return Err("x_cor, y_cor and z_cor only apply to .npy input".to_string());
+                }
+                input
+            }
+            (Some(_), _, _, _) => {
+                return Err("jx, jy and jz exclude npz and gimic".to_string());
+            }
+            (None, Some(jx), Some(jy), Some(jz)) => {
+                let j: [PathBuf; 3] = [jx.into(), jy.into(), jz.into()];
+                let npy = j
+                    .iter()
+                    .all(|path| path.extension().map_or(false, |ext| ext == "npy"));
+                match (self.x_cor, self.y_cor, self.z_cor) {
+                    (Some(x), Some(y), Some(z)) if npy => Input::Npy {
+                        j,
+                        cor: [x.into(), y.into(), z.into()],
+                    },
+                    (None, None, None) if !npy => Input::Cubes(j),
+                    _ if npy => return Err("x_cor, y_cor and z_cor have to be given".to_string()),
+                    _ => return Err("x_cor, y_cor and z_cor only apply to .npy input".to_string()),
+                }
+            }
+            _ => return Err("jx, jy and jz, npz or gimic have to be given".to_string()),
+        };
+        match (&input, self.units) {
+            (Input::Cubes(_), Some(units)) | (Input::Gimic(_), Some(units))
+                if units != Units::Atomic =>
+            {
+                return Err(
+                    "cube and GIMIC files are in atomic units, units has to be au".to_string(),
+                )
+            }
+            _ => {}
+        }
+
+        let solver = match (self.solver.unwrap_or_default(), self.theta) {
+            (Solver::Tree(_), theta) => Solver::Tree(theta.unwrap_or(DEFAULT_THETA)),
+            (_, Some(_)) => return Err("theta only applies to solver = tree".to_string()),
+            (solver, None) => solver,
+        };
+        let singularity = match self.singularity {
+            Some(value) => Singularity::parse(&value, self.softening)?,
+            None => Singularity::default(),
+        };
+
+        let targets = match self.points {
+            Some(_) if self.target.iter().any(Option::is_some) => {
+                return Err("points and target_x, target_y, target_z exclude each other".to_string())
+            }
+            Some(points) => Targets::Points(points.into()),
+            None => Targets::Grid(self.target),
+        };
+        let on_j_grid = match &targets {
+            Targets::Grid(target) => target.iter().all(Option::is_none),
+            Targets::Points(_) => false,
+        };
+        if solver == Solver::Fft && !on_j_grid {
+            return Err("solver = fft only calculates B on the grid of J".to_string());
+        }
+        if let (Targets::Points(_), Some(formats)) = (&targets, &self.formats) {
+            if formats.iter().any(|format| *format != Format::Npz) {
+                return Err("format = vtr, vti and cube need a target grid, not points".to_string());
+            }
+        }
+
+        Ok(Config {
+            input,
+            units: self.units.unwrap_or_default(),
+            convention: self.convention.unwrap_or_default(),
+            center: self.center.unwrap_or([0.0; 3]),
+            solver,
+            singularity,
+            backend: self.backend.unwrap_or_default(),
+            targets,
+            output: self.output.unwrap_or_else(|| "biot".to_string()).into(),
+            formats: self.formats.unwrap_or_else(|| vec![Format::Vtr]),
+        })
+    }
+}
+
+fn number(key: &str, value: &str) -> Result<f64, String> {
+    value
+        .parse()
+        .map_err(|_| format!("{} has to be a number, found '{}'", key, value))
+}
+
+fn numbers(key: &str, value: &str) -> Result<Vec<f64>, String> {
+    value
+        .split_whitespace()
+        .map(|value| number(key, value))
+        .collect()
+}
+
+// Parses `start stop count` into `count` evenly spaced coordinates from start to
+// stop, or a single coordinate.
+fn range(key: &str, value: &str) -> Result<Vec<f64>, String> {
+    match numbers(key, value)?.as_slice() {
+        &[start] => Ok(vec![start]),
+        &[start, stop, count] if count >= 2.0 && count.fract() == 0.0 => {
+            let n = count as usize;
+            let h = (stop - start) / (n - 1) as f64;
+            Ok((0..n).map(|i| start + i as f64 * h).collect())
+        }
+        _ => Err(format!(
+            "{} has to be a coordinate or 'start stop count' with count > 1",
+            key
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings(lines: &[&str]) -> Settings {
+        let mut settings = Settings::default();
+        for line in lines {
+            settings.set(line).unwrap();
+        }
+        settings
+    }
+
+    fn checked(lines: &[&str]) -> Config {
+        match settings(lines).check() {
+            Ok(config) => config,
+            Err(err) => panic!("{}", err),
+        }
+    }
+
+    fn check_error(lines: &[&str]) -> String {
+        match settings(lines).check() {
+            Ok(_) => panic!("{:?} accepted", lines),
+            Err(err) => err,
+        }
+    }
+
+    const CUBES: [&str; 3] = ["jx = x.cube", "jy = y.cube", "jz = z.cube"];
+
+    #[test]
+    fn sets_known_keys() {
+        let mut lines = CUBES.to_vec();
+        lines.extend_from_slice(&[
+            "# a comment",
+            "",
+            "convention = electron_flux  # trailing comment",
+            "center = 1 -2 0.5",
+            "solver = tree",
+            "theta = 0.3",
+            "singularity = soften",
+            "softening = 0.1",
+            "backend = scalar",
+            "target_z = 0",
+            "output = out/b",
+            "format = vti, npz cube",
+        ]);
+        let config = checked(&lines);
+
+        match config.input {
+            Input::Cubes(paths) => assert_eq!(paths[1], PathBuf::from("y.cube")),
+            _ => panic!("expected cube input"),
+        }
+        assert_eq!(config.units, Units::Atomic);
+        assert_eq!(config.convention, Convention::ElectronFlux);
+        assert_eq!(config.center, [1.0, -2.0, 0.5]);
+        assert_eq!(config.solver, Solver::Tree(0.3));
+        assert_eq!(config.singularity, Singularity::Soften(0.1));
+        assert_eq!(config.backend, Backend::Scalar);
+        match config.targets {
+            Targets::Grid([None, None, Some(z)]) => assert_eq!(z, vec![0.0]),
+            _ => panic!("expected a grid in the plane z = 0"),
+        }
+        assert_eq!(config.output, PathBuf::from("out/b"));
+        assert_eq!(config.formats, vec![Format::Vti, Format::Npz, Format::Cube]);
+    }
+
+    #[test]
+    fn defaults() {
+        let config = checked(&["npz = j.npz"]);
+        assert_eq!(config.units, Units::Atomic);
+        assert_eq!(config.convention, Convention::Current);
+        assert_eq!(config.center, [0.0; 3]);
+        assert_eq!(config.solver, Solver::Direct);
+        assert_eq!(config.singularity, Singularity::Exclude);
+        assert_eq!(config.backend, Backend::Auto);
+        assert_eq!(config.output, PathBuf::from("biot"));
+        assert_eq!(config.formats, vec![Format::Vtr]);
+
+        let config = checked(&["npz = j.npz", "solver = tree"]);
+        assert_eq!(config.solver, Solver::Tree(DEFAULT_THETA));
+    }
+
+    #[test]
+    fn rejects_unknown_keys_and_values() {
+        let mut settings = Settings::default();
+        assert!(settings
+            .set("jx x.cube")
+            .unwrap_err()
+            .contains("key = value"));
+        assert!(settings
+            .set("colour = red")
+            .unwrap_err()
+            .contains("'colour'"));
+        assert!(settings.set("units = furlongs").is_err());
+        assert!(settings.set("solver = magic").is_err());
+        assert!(settings.set("theta = wide").unwrap_err().contains("number"));
+        assert!(settings
+            .set("center = 0 0")
+            .unwrap_err()
+            .contains("3 coordinates"));
+        assert!(settings.set("format = vtr, png").is_err());
+    }
+
+    #[test]
+    fn parses_ranges() {
+        assert_eq!(range("x", "2.5"), Ok(vec![2.5]));
+        assert_eq!(range("x", "-1 1 5"), Ok(vec![-1.0, -0.5, 0.0, 0.5, 1.0]));
+        for value in &["", "0 1", "0 1 1", "0 1 2.5", "0 1 -3", "0 1 2 3", "a b c"] {
+            assert!(range("target_x", value).is_err(), "'{}'", value);
+        }
+    }
+
+    #[test]
+    fn requires_input() {
+        assert!(check_error(&[]).contains("have to be given"));
+        assert!(check_error(&["jx = x.cube", "jy = y.cube"]).contains("have to be given"));
+        assert!(check_error(&["jx = x.npy", "jy = y.npy", "jz = z.npy"]).contains("x_cor"));
+        assert!(check_error(&["npz = j.npz", "gimic = jvec.txt"]).contains("exclude"));
+        assert!(check_error(&["npz = j.npz", "x_cor = x.npy"]).contains("only apply"));
+
+        let config = checked(&[
+            "jx = x.npy",
+            "jy = y.npy",
+            "jz = z.npy",
+            "x_cor = xc.npy",
+            "y_cor = yc.npy",
+            "z_cor = zc.npy",
+            "units = si",
+        ]);
+        match config.input {
+            Input::Npy { cor, .. } => assert_eq!(cor[2], PathBuf::from("zc.npy")),
+            _ => panic!("expected .npy input"),
+        }
+    }
+
+    #[test]
+    fn rejects_inconsistent_settings() {
+        let mut cubes = CUBES.to_vec();
+        cubes.push("units = si");
+        assert!(check_error(&cubes).contains("atomic units"));
+
+        assert!(check_error(&["gimic = jvec.txt", "units = gaussian"]).contains("atomic units"));
+        assert!(check_error(&["npz = j.npz", "singularity = soften"]).contains("softening"));
+        assert!(
+            check_error(&["npz = j.npz", "singularity = soften", "softening = 0"])
+                .contains("softening")
+        );
+        assert!(
+            check_error(&["npz = j.npz", "points = p.npy", "target_x = 0"]).contains("exclude")
+        );
+
+        let config = checked(&["npz = j.npz", "singularity = soften:0.2"]);
+        assert_eq!(config.singularity, Singularity::Soften(0.2));
+    }
+
+    #[test]
+    fn rejects_settings_that_do_not_apply() {
+        assert!(check_error(&["npz = j.npz", "theta = 0.3"]).contains("solver = tree"));
+        assert!(
+            check_error(&["npz = j.npz", "solver = fft", "theta = 0.3"]).contains("solver = tree")
+        );
+        assert!(check_error(&["npz = j.npz", "points = p.npy", "format = vtr"]).contains("points"));
+        assert!(
+            check_error(&["npz = j.npz", "points = p.npy", "format = npz, cube"])
+                .contains("points")
+        );
+        assert!(check_error(&["npz = j.npz", "solver = fft", "points = p.npy"]).contains("fft"));
+        assert!(check_error(&["npz = j.npz", "solver = fft", "target_z = 0"]).contains("fft"));
+
+        let config = checked(&["npz = j.npz", "points = p.npy", "format = npz"]);
+        assert_eq!(config.formats, vec![Format::Npz]);
+        let config = checked(&["npz = j.npz", "solver = fft"]);
+        assert_eq!(config.solver, Solver::Fft);
+    }
+}
@@ -0,0 +1,307 @@
+//! Command-line interface of the Biot-Savart solver.
+//!
+//! Reads a current density from cube, .npy, .npz or GIMIC files, calculates the
+//! magnetic field on a grid or at points and the magnetization, and writes them
+//! to files. The settings are read from a configuration file, and can be
+//! overridden by `key=value` arguments.
+
+extern crate biot_savart;
+extern crate log;
+extern crate ndarray;
+
+mod config;
+
+use std::env;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+
+use biot_savart::{read_npy, CurrentDensity, NpzWriter, Units, VectorField};
+use log::{info, warn, Level, LevelFilter, Log, Metadata, Record};
+use ndarray::prelude::*;
+
+use crate::config::{Config, Format, Input, Targets};
+
+const USAGE: &str = "\
+usage: biot-savart [-q] [-v] [CONFIG] [KEY=VALUE]...
+
+Calculates the magnetic field and the magnetization of a current density.
+
+Settings, given one per line in CONFIG, or as arguments:
+  jx, jy, jz         files of the current density, cube or .npy
+  x_cor, y_cor, z_cor  .npy files of the coordinates, for .npy input
+  npz                .npz file of jx, jy, jz, x_cor, y_cor and z_cor, instead
+                     of the files above
+  gimic              jvec.txt or jvec.vti file written by GIMIC, instead of
+                     the files above
+  units              au (default), si or gaussian
+  convention         current (default) if J is the electric current density,
+                     or electron_flux if it is the flux of the electrons
+  center             center of the magnetization, default 0 0 0
+  solver             direct (default), tree, or fft for B on the grid of J
+  theta              opening angle of the tree code, default 0.5
+  singularity        exclude (default), analytic, or soften with the length
+                     given by softening or as soften:<length>
+  softening          softening length, for singularity = soften
+  backend            auto (default), scalar, sse2, sse41 or avx2
+  target_x, target_y, target_z
+                     target grid as 'start stop count' or a single coordinate,
+                     the grid of J along dimensions that are not given
+  points             .npy file of an Nx3 array of target points, instead of
+                     a target grid
+  output             start of the names of the output files, default biot
+  format             formats of the output: vtr (default), vti and cube for B
+                     on a grid, and npz for B together with the magnetization
+                     and the settings
+
+Options:
+  -q  do not report progress
+  -v  log debug messages";
+
+// Writes log records to stderr
+struct StderrLogger;
+
+impl Log for StderrLogger {
+    fn enabled(&self, _: &Metadata) -> bool {
+        true
+    }
+
+    fn log(&self, record: &Record) {
+        if record.level() <= Level::Warn {
+            eprintln!(
+                "{}: {}",
+                record.level().to_string().to_lowercase(),
+                record.args()
+            );
+        } else {
+            eprintln!("{}", record.args());
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+fn main() {
+    let mut quiet = false;
+    let mut level = LevelFilter::Info;
+    let mut path = None;
+    let mut overrides = Vec::new();
+    for arg in env::args().skip(1) {
+        match arg.as_str() {
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return;
+            }
+            "-q" => quiet = true,
+            "-v" => level = LevelFilter::Debug,
+            _ if arg.contains('=') => overrides.push(arg),
+            _ if path.is_none() => path = Some(arg),
+            _ => fail(&format!("unexpected argument '{}'\n\n{}", arg, USAGE)),
+        }
+    }
+    if path.is_none() && overrides.is_empty() {
+        fail(USAGE);
+    }
+
+    log::set_boxed_logger(Box::new(StderrLogger)).unwrap();
+    log::set_max_level(if quiet { LevelFilter::Warn } else { level });
+
+    let result = Config::read(path.as_ref().map(String::as_str), &overrides)
+        .and_then(|config| run(&config, quiet).map_err(|err| err.to_string()));
+    if let Err(err) = result {
+        fail(&err);
+    }
+}
+
+fn fail(message: &str) -> ! {
+    eprintln!("error: {}", message);
+    process::exit(1);
+}
+
+fn run(config: &Config, quiet: bool) -> biot_savart::Result<()> {
+    let mut density = match &config.input {
+        Input::Cubes([jx, jy, jz]) => CurrentDensity::from_cubes(jx, jy, jz)?,
+        Input::Npy { j, cor } => CurrentDensity::from_npy(j.clone(), cor.clone())?,
+        Input::Npz(path) => CurrentDensity::from_npz(path)?,
+        Input::Gimic(path) => CurrentDensity::from_gimic(path)?,
+    };
+    // cube and GIMIC files are in atomic units, which the configuration checks
+    density.units = config.units;
+    let units = density.units;
+
+    let report = |done: usize, total: usize| {
+        eprint!("\rcalculating B: {}/{}", done, total);
+        if done == total {
+            eprintln!();
+        }
+    };
+    let mut solver = density
+        .solver()?
+        .convention(config.convention)
+        .solver(config.solver)
+        .singularity(config.singularity)
+        .backend(config.backend);
+    if !quiet {
+        solver = solver.progress(&report);
+    }
+
+    let m = solver.magnetization(config.center);
+    let path = output_path(&config.output, "_m.txt");
+    write_text(&path, |w| {
+        writeln!(
+            w,
+            "# magnetization around {:?}, {} units, J as {}",
+            config.center, units, config.convention
+        )?;
+        writeln!(w, "{:e} {:e} {:e}", m[0], m[1], m[2])
+    })?;
+    info!("wrote {}", path.display());
+
+    let mut npz = if config.formats.contains(&Format::Npz) {
+        let mut npz = NpzWriter::create(output_path(&config.output, ".npz"))?;
+        npz.add_str("units", &units.to_string())?;
+        npz.add_str("convention", &config.convention.to_string())?;
+        npz.add_str("solver", &config.solver.to_string())?;
+        npz.add_str("singularity", &config.singularity.to_string())?;
+        npz.add_array("center", aview1(&config.center))?;
+        npz.add_array("m", aview1(&m))?;
+        Some(npz)
+    } else {
+        None
+    };
+
+    match &config.targets {
+        Targets::Grid(target) => {
+            let on_j_grid = target.iter().all(Option::is_none);
+            let cor = [&density.x_cor, &density.y_cor, &density.z_cor];
+            let target: Vec<&[f64]> = target
+                .iter()
+                .zip(&cor)
+                .map(|(target, cor)| target.as_ref().unwrap_or(*cor).as_slice())
+                .collect();
+            // only the grid of J itself can be calculated by FFT
+            let (b_x, b_y, b_z) = if on_j_grid {
+                solver.field()?
+            } else {
+                solver.field_on_grid(target[0], target[1], target[2])?
+            };
+            write_grid(
+                config,
+                units,
+                &density,
+                &target,
+                (&b_x, &b_y, &b_z),
+                npz.as_mut(),
+            )?;
+        }
+        Targets::Points(points_path) => {
+            let points = read_npy(points_path)?
+                .into_dimensionality::<Ix2>()
+                .ok()
+                .filter(|points| points.ncols() == 3)
+                .ok_or_else(|| biot_savart::Error::InvalidFile {
+                    path: points_path.display().to_string(),
+                    message: "expected an Nx3 array of points".to_string(),
+                })?;
+            let b = solver.field_at(points.view())?;
+            if let Some(npz) = npz.as_mut() {
+                npz.add_array("points", points.view())?;
+                npz.add_array("B", b.view())?;
+            }
+
+            let path = output_path(&config.output, "_points.txt");
+            write_text(&path, |w| {
+                writeln!(
+                    w,
+                    "# x y z Bx By Bz, {} units, J as {}",
+                    units, config.convention
+                )?;
+                for (r, b) in points.genrows().into_iter().zip(b.genrows()) {
+                    writeln!(
+                        w,
+                        "{:e} {:e} {:e} {:e} {:e} {:e}",
+                        r[0], r[1], r[2], b[0], b[1], b[2]
+                    )?;
+                }
+                Ok(())
+            })?;
+            info!("wrote {}", path.display());
+        }
+    }
+
+    if let Some(npz) = npz {
+        npz.finish()?;
+        info!("wrote {}", output_path(&config.output, ".npz").display());
+    }
+    Ok(())
+}
+
+// Writes B on a target grid in the formats of the configuration.
+fn write_grid(
+    config: &Config,
+    units: Units,
+    density: &CurrentDensity,
+    target: &[&[f64]],
+    b: (&Array3<f64>, &Array3<f64>, &Array3<f64>),
+    mut npz: Option<&mut NpzWriter>,
+) -> biot_savart::Result<()> {
+    let field = VectorField::new(
+        b.0.view(),
+        b.1.view(),
+        b.2.view(),
+        target[0],
+        target[1],
+        target[2],
+    )?;
+
+    for format in &config.formats {
+        let paths = match format {
+            Format::Cube if units != Units::Atomic => {
+                warn!("cube files are in bohr, skipping them for {} units", units);
+                Vec::new()
+            }
+            Format::Cube => field.write_cubes(&config.output, "B", &density.atoms)?,
+            Format::Vtr => {
+                let path = output_path(&config.output, ".vtr");
+                field.write_vtr(&path, "B")?;
+                vec![path]
+            }
+            Format::Vti => {
+                let path = output_path(&config.output, ".vti");
+                field.write_vti(&path, "B")?;
+                vec![path]
+            }
+            // written once the rest of the archive is
+            Format::Npz => {
+                if let Some(npz) = npz.as_mut() {
+                    field.add_to_npz(npz, "B")?;
+                }
+                Vec::new()
+            }
+        };
+        for path in paths {
+            info!("wrote {}", path.display());
+        }
+    }
+    Ok(())
+}
+
+fn output_path(stem: &Path, suffix: &str) -> PathBuf {
+    let mut path = stem.as_os_str().to_owned();
+    path.push(suffix);
+    path.into()
+}
+
+fn write_text<F>(path: &Path, write: F) -> biot_savart::Result<()>
+where
+    F: FnOnce(&mut BufWriter<File>) -> io::Result<()>,
+{
+    File::create(path)
+        .and_then(|file| {
+            let mut w = BufWriter::new(file);
+            write(&mut w)?;
+            w.flush()
+        })
+        .map_err(|err| biot_savart::Error::Io(format!("{}: {}", path.display(), err)))
+}
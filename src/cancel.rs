@@ -0,0 +1,49 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// Flag to stop a running calculation from another thread.
+///
+/// Clones share the flag, so a clone can be handed to the solver with
+/// [`BiotSavart::cancel_token`](struct.BiotSavart.html#method.cancel_token)
+/// and cancelled from elsewhere. A cancelled calculation returns
+/// [`Error::Cancelled`](enum.Error.html#variant.Cancelled).
+#[derive(Clone, Debug)]
+pub struct CancelToken {
+    // The flag of the token first, followed by those of the tokens it was
+    // derived from
+    flags: Vec<Arc<AtomicBool>>,
+}
+
+impl CancelToken {
+    /// Returns a token that is not cancelled.
+    pub fn new() -> Self {
+        CancelToken {
+            flags: vec![Arc::new(AtomicBool::new(false))],
+        }
+    }
+
+    /// Requests the calculations using the token to stop.
+    pub fn cancel(&self) {
+        self.flags[0].store(true, Ordering::Relaxed);
+    }
+
+    /// Returns true once [`cancel`](#method.cancel) has been called on the
+    /// token or one of its clones.
+    pub fn is_cancelled(&self) -> bool {
+        self.flags.iter().any(|flag| flag.load(Ordering::Relaxed))
+    }
+
+    // Returns a token that is cancelled along with this one, but that can be
+    // cancelled on its own without cancelling this one.
+    pub(crate) fn child(&self) -> Self {
+        let mut flags = vec![Arc::new(AtomicBool::new(false))];
+        flags.extend(self.flags.iter().cloned());
+        CancelToken { flags }
+    }
+}
+
+impl Default for CancelToken {
+    fn default() -> Self {
+        CancelToken::new()
+    }
+}
@@ -0,0 +1,58 @@
+use std::fmt;
+use std::str::FromStr;
+
+/// Sign convention of the current density.
+///
+/// Codes differ in whether they write the electric current density or the flux
+/// of the electrons, which differ in sign because of the negative charge of the
+/// electron. Reading J in the wrong convention flips the sign of both B and m.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Convention {
+    /// J is the electric current density, so B = k ∫ J × r / r³ dV and
+    /// m = k ∫ r × J dV.
+    Current,
+    /// J is the flux of the electrons, the negative of the electric current
+    /// density, as used in "Local Current Density Calculations for Molecular
+    /// Films from Ab Initio", J. Chem. Theory Comput. 2015, 11, 5161.
+    ElectronFlux,
+}
+
+impl Convention {
+    // Factor turning J into the electric current density
+    pub(crate) fn sign(self) -> f64 {
+        match self {
+            Convention::Current => 1.0,
+            Convention::ElectronFlux => -1.0,
+        }
+    }
+}
+
+impl Default for Convention {
+    fn default() -> Self {
+        Convention::Current
+    }
+}
+
+impl FromStr for Convention {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "current" => Ok(Convention::Current),
+            "electron_flux" | "electron" => Ok(Convention::ElectronFlux),
+            _ => Err(format!(
+                "unknown convention '{}', expected 'current' or 'electron_flux'",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Convention {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Convention::Current => write!(f, "current"),
+            Convention::ElectronFlux => write!(f, "electron_flux"),
+        }
+    }
+}
@@ -0,0 +1,274 @@
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Lines, Write};
+use std::path::Path;
+use std::str::FromStr;
+
+use ndarray::prelude::*;
+
+use crate::error::{check_shape, Error, Result};
+use crate::fft::uniform_spacing;
+use crate::units::BOHR_IN_ANGSTROM;
+
+/// Atom listed in the header of a cube file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Atom {
+    /// Atomic number
+    pub number: i32,
+    /// Nuclear charge, usually equal to the atomic number
+    pub charge: f64,
+    /// Position in bohr
+    pub position: [f64; 3],
+}
+
+/// Gaussian cube file holding a single value at each grid point.
+///
+/// Grid point (i, j, k) is at `origin + i * axes[0] + j * axes[1] + k * axes[2]`.
+/// All lengths are in bohr, files in Ångström (negative voxel counts) are
+/// converted when they are read.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Cube {
+    /// The two comment lines at the top of the file
+    pub comments: [String; 2],
+    pub origin: [f64; 3],
+    /// Voxel vectors along the first, second and third dimension of `data`
+    pub axes: [[f64; 3]; 3],
+    pub atoms: Vec<Atom>,
+    pub data: Array3<f64>,
+}
+
+impl Cube {
+    /// Creates a cube holding `data` on the grid with coordinates `cor`, in bohr.
+    ///
+    /// Returns [`Error::ShapeMismatch`](enum.Error.html#variant.ShapeMismatch)
+    /// if `data` does not match the coordinates, and
+    /// [`Error::NonUniform`](enum.Error.html#variant.NonUniform) if they are not
+    /// uniformly spaced, which cube files require. The comments are empty.
+    pub fn new(data: Array3<f64>, cor: [&[f64]; 3], atoms: Vec<Atom>) -> Result<Cube> {
+        check_shape(
+            "data",
+            data.shape(),
+            &[cor[0].len(), cor[1].len(), cor[2].len()],
+        )?;
+        let spacing = |cor: &[f64]| uniform_spacing(cor).ok_or(Error::NonUniform);
+        let h = [spacing(cor[0])?, spacing(cor[1])?, spacing(cor[2])?];
+        let first = |cor: &[f64]| cor.first().cloned().unwrap_or(0.0);
+
+        Ok(Cube {
+            comments: [String::new(), String::new()],
+            origin: [first(cor[0]), first(cor[1]), first(cor[2])],
+            axes: [[h[0], 0.0, 0.0], [0.0, h[1], 0.0], [0.0, 0.0, h[2]]],
+            atoms,
+            data,
+        })
+    }
+
+    /// Reads a cube file.
+    ///
+    /// Returns [`Error::Io`](enum.Error.html#variant.Io) if the file cannot be
+    /// read, and [`Error::InvalidFile`](enum.Error.html#variant.InvalidFile) if
+    /// it is not a cube file or holds more than one value per grid point.
+    pub fn read<P: AsRef<Path>>(path: P) -> Result<Cube> {
+        let path = path.as_ref();
+        let file = File::open(path).map_err(|err| Error::io(path, err))?;
+        let mut parser = Parser {
+            path,
+            lines: BufReader::new(file).lines(),
+            line: 0,
+        };
+        parser.cube()
+    }
+
+    /// Returns the x-, y- and z- coordinates of the grid, or `None` if the voxel
+    /// vectors are not along x, y and z, so the grid is not rectilinear.
+    pub fn coordinates(&self) -> Option<[Vec<f64>; 3]> {
+        for a in 0..3 {
+            let length = self.axes[a][a].abs();
+            let off_axis = (0..3).filter(|&b| b != a).map(|b| self.axes[a][b].abs());
+            if length == 0.0 || off_axis.fold(0.0, f64::max) > 1e-10 * length {
+                return None;
+            }
+        }
+
+        let (nx, ny, nz) = self.data.dim();
+        let cor = |a: usize, n: usize| -> Vec<f64> {
+            (0..n)
+                .map(|i| self.origin[a] + i as f64 * self.axes[a][a])
+                .collect()
+        };
+        Some([cor(0, nx), cor(1, ny), cor(2, nz)])
+    }
+
+    /// Writes the cube file, in bohr.
+    pub fn write<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        File::create(path)
+            .and_then(|file| self.write_to(BufWriter::new(file)))
+            .map_err(|err| Error::io(path, err))
+    }
+
+    fn write_to<W: Write>(&self, mut w: W) -> io::Result<()> {
+        writeln!(w, "{}", self.comments[0])?;
+        writeln!(w, "{}", self.comments[1])?;
+        let origin = self.origin;
+        writeln!(
+            w,
+            "{:5}{:12.6}{:12.6}{:12.6}",
+            self.atoms.len(),
+            origin[0],
+            origin[1],
+            origin[2]
+        )?;
+        let counts = self.data.shape();
+        for (n, axis) in counts.iter().zip(&self.axes) {
+            writeln!(w, "{:5}{:12.6}{:12.6}{:12.6}", n, axis[0], axis[1], axis[2])?;
+        }
+        for atom in &self.atoms {
+            let r = atom.position;
+            writeln!(
+                w,
+                "{:5}{:12.6}{:12.6}{:12.6}{:12.6}",
+                atom.number, atom.charge, r[0], r[1], r[2]
+            )?;
+        }
+
+        // six values per line, starting a new line for every line along z
+        for line in self.data.genrows() {
+            for (k, value) in line.iter().enumerate() {
+                write!(w, " {:12.5E}", value)?;
+                if k % 6 == 5 || k == line.len() - 1 {
+                    writeln!(w)?;
+                }
+            }
+        }
+        w.flush()
+    }
+}
+
+// Reads a cube file line by line, keeping track of the line number for errors.
+struct Parser<'p, R> {
+    path: &'p Path,
+    lines: Lines<R>,
+    line: usize,
+}
+
+impl<'p, R: BufRead> Parser<'p, R> {
+    fn cube(&mut self) -> Result<Cube> {
+        let comments = [self.next_line()?, self.next_line()?];
+
+        let header = self.values()?;
+        if header.len() < 4 {
+            return Err(self.invalid("expected the number of atoms and the origin"));
+        }
+        let natoms = header[0] as i64;
+        let mut nval = header.get(4).map_or(1, |&nval| nval as i64);
+
+        // the number of voxels is negative for files in Ångström
+        let mut scale = 1.0;
+        let mut counts = [0usize; 3];
+        let mut axes = [[0f64; 3]; 3];
+        for a in 0..3 {
+            let values = self.values()?;
+            if values.len() != 4 || values[0] == 0.0 || values[0].fract() != 0.0 {
+                return Err(self.invalid("expected the number of voxels and the voxel vector"));
+            }
+            if values[0] < 0.0 {
+                scale = 1.0 / BOHR_IN_ANGSTROM;
+            }
+            counts[a] = values[0].abs() as usize;
+            axes[a] = [values[1], values[2], values[3]];
+        }
+        let scaled = |r: &[f64]| [r[0] * scale, r[1] * scale, r[2] * scale];
+
+        let mut atoms = Vec::with_capacity(natoms.abs() as usize);
+        for _ in 0..natoms.abs() {
+            let values = self.values()?;
+            if values.len() != 5 {
+                return Err(self.invalid("expected the atomic number, charge and position"));
+            }
+            atoms.push(Atom {
+                number: values[0] as i32,
+                charge: values[1],
+                position: scaled(&values[2..]),
+            });
+        }
+
+        // a negative number of atoms is followed by the ids of the data sets
+        if natoms < 0 {
+            match self.values()?.first() {
+                Some(&count) => nval = count as i64,
+                None => return Err(self.invalid("expected the number of data sets")),
+            }
+        }
+        if nval != 1 {
+            return Err(self.invalid(&format!(
+                "holds {} values per grid point, only 1 is supported",
+                nval
+            )));
+        }
+
+        let len = counts[0] * counts[1] * counts[2];
+        let mut data = Vec::with_capacity(len);
+        while data.len() < len {
+            match self.try_line()? {
+                Some(line) => data.extend(self.parse(&line)?),
+                None => break,
+            }
+        }
+        if data.len() != len {
+            return Err(self.invalid(&format!(
+                "holds {} values, expected {}x{}x{}",
+                data.len(),
+                counts[0],
+                counts[1],
+                counts[2]
+            )));
+        }
+
+        Ok(Cube {
+            comments,
+            origin: scaled(&header[1..4]),
+            axes: [scaled(&axes[0]), scaled(&axes[1]), scaled(&axes[2])],
+            atoms,
+            data: Array3::from_shape_vec((counts[0], counts[1], counts[2]), data).unwrap(),
+        })
+    }
+
+    fn try_line(&mut self) -> Result<Option<String>> {
+        match self.lines.next() {
+            Some(line) => {
+                self.line += 1;
+                line.map(Some).map_err(|err| Error::io(self.path, err))
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn next_line(&mut self) -> Result<String> {
+        match self.try_line()? {
+            Some(line) => Ok(line),
+            None => Err(self.invalid("unexpected end of file")),
+        }
+    }
+
+    fn values(&mut self) -> Result<Vec<f64>> {
+        let line = self.next_line()?;
+        self.parse(&line)
+    }
+
+    // Parses the numbers of a line, which may use Fortran exponents, 1.0D-03.
+    fn parse(&self, line: &str) -> Result<Vec<f64>> {
+        line.split_whitespace()
+            .map(|token| {
+                f64::from_str(&token.replace(|c| c == 'D' || c == 'd', "E"))
+                    .map_err(|_| self.invalid(&format!("'{}' is not a number", token)))
+            })
+            .collect()
+    }
+
+    fn invalid(&self, message: &str) -> Error {
+        Error::InvalidFile {
+            path: self.path.display().to_string(),
+            message: format!("line {}: {}", self.line, message),
+        }
+    }
+}
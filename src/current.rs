@@ -0,0 +1,139 @@
+use ndarray::prelude::*;
+
+use crate::error::{Error, Result};
+use crate::grid::trilinear;
+
+/// Half-plane that the current strength is integrated over, as in GIMIC.
+///
+/// The plane is spanned by `direction` and `up`. It starts at the line through
+/// `origin` along `up`, and reaches `length` along `direction` and `height`
+/// above and below `origin` along `up`. Points lie `spacing` apart, or a little
+/// less to fill the plane evenly.
+///
+/// For the ring current of a ring in the xy plane in a field along z, `up` is
+/// z, `origin` the center of the ring, and `direction` points from it through
+/// the middle of a bond, so the plane cuts the bond perpendicularly. `up` is
+/// made perpendicular to `direction` if it is not.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct HalfPlane {
+    pub origin: [f64; 3],
+    pub direction: [f64; 3],
+    pub up: [f64; 3],
+    pub length: f64,
+    pub height: f64,
+    pub spacing: f64,
+}
+
+/// Current through a half-plane, as returned by
+/// [`BiotSavart::current_strength`](struct.BiotSavart.html#method.current_strength).
+///
+/// Currents are in nA/T, and positive when diatropic, i.e. along
+/// `direction` × `up`, the direction of the current that a field along `up`
+/// induces in a diamagnetic ring.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CurrentStrength {
+    /// Net current, the sum of the diatropic and the paratropic currents
+    pub total: f64,
+    /// Sum of the positive contributions
+    pub diatropic: f64,
+    /// Sum of the negative contributions
+    pub paratropic: f64,
+    /// Distances from the origin along `direction` of the profile
+    pub distances: Vec<f64>,
+    /// dJ/ds, the current through the plane per unit length along `direction`,
+    /// at each distance
+    pub profile: Vec<f64>,
+}
+
+// Integrates J · n over the half-plane with the trapezoidal rule, where J is
+// interpolated trilinearly, and vanishes outside of the grid. The integrals are
+// scaled by `prefactor`.
+pub(crate) fn current_strength(
+    j: [&ArrayView3<f64>; 3],
+    cor: [&[f64]; 3],
+    prefactor: f64,
+    plane: &HalfPlane,
+) -> Result<CurrentStrength> {
+    if !(plane.length > 0.0 && plane.height > 0.0 && plane.spacing > 0.0) {
+        return Err(Error::InvalidPlane(
+            "length, height and spacing have to be positive",
+        ));
+    }
+    let direction =
+        normalize(plane.direction).ok_or(Error::InvalidPlane("direction has to be nonzero"))?;
+    let up = normalize(sub(plane.up, scale(dot(plane.up, direction), direction)))
+        .ok_or(Error::InvalidPlane("up has to be nonparallel to direction"))?;
+    let normal = cross(direction, up);
+
+    let (ns, ds) = steps(plane.length, plane.spacing);
+    let (nt, dt) = steps(2.0 * plane.height, plane.spacing);
+    let weight = |i: usize, n: usize| if i == 0 || i == n { 0.5 } else { 1.0 };
+
+    let mut strength = CurrentStrength {
+        total: 0.0,
+        diatropic: 0.0,
+        paratropic: 0.0,
+        distances: (0..=ns).map(|i| i as f64 * ds).collect(),
+        profile: Vec::with_capacity(ns + 1),
+    };
+    for (i, s) in strength.distances.iter().enumerate() {
+        let mut line = 0.0;
+        for k in 0..=nt {
+            let t = k as f64 * dt - plane.height;
+            let point = add(plane.origin, add(scale(*s, direction), scale(t, up)));
+            let flux = match trilinear(j, cor, point) {
+                Some(j) => prefactor * dot(j, normal) * weight(k, nt) * dt,
+                None => continue,
+            };
+            line += flux;
+            if flux > 0.0 {
+                strength.diatropic += flux * weight(i, ns) * ds;
+            } else {
+                strength.paratropic += flux * weight(i, ns) * ds;
+            }
+        }
+        strength.profile.push(line);
+    }
+    strength.total = strength.diatropic + strength.paratropic;
+    Ok(strength)
+}
+
+// Splits `length` into the fewest intervals no longer than `spacing`, and
+// returns their number and length.
+fn steps(length: f64, spacing: f64) -> (usize, f64) {
+    let n = ((length / spacing).ceil() as usize).max(1);
+    (n, length / n as f64)
+}
+
+fn add(a: [f64; 3], b: [f64; 3]) -> [f64; 3] {
+    [a[0] + b[0], a[1] + b[1], a[2] + b[2]]
+}
+
+fn sub(a: [f64; 3], b: [f64; 3]) -> [f64; 3] {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+fn scale(s: f64, a: [f64; 3]) -> [f64; 3] {
+    [s * a[0], s * a[1], s * a[2]]
+}
+
+fn dot(a: [f64; 3], b: [f64; 3]) -> f64 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+fn cross(a: [f64; 3], b: [f64; 3]) -> [f64; 3] {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
+
+fn normalize(a: [f64; 3]) -> Option<[f64; 3]> {
+    let norm = dot(a, a).sqrt();
+    if norm > 1e-12 {
+        Some(scale(1.0 / norm, a))
+    } else {
+        None
+    }
+}
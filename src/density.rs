@@ -0,0 +1,159 @@
+use std::path::Path;
+
+use ndarray::prelude::*;
+
+use crate::cube::{Atom, Cube};
+use crate::error::{Error, Result};
+use crate::gimic;
+use crate::npy::{read_npy, read_npz};
+use crate::solver::BiotSavart;
+use crate::units::Units;
+
+/// Current density read from files, owning J and the coordinates of its grid.
+///
+/// [`solver`](#method.solver) creates a [`BiotSavart`](struct.BiotSavart.html)
+/// borrowing it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CurrentDensity {
+    pub jx: Array3<f64>,
+    pub jy: Array3<f64>,
+    pub jz: Array3<f64>,
+    pub x_cor: Vec<f64>,
+    pub y_cor: Vec<f64>,
+    pub z_cor: Vec<f64>,
+    /// Atoms of the molecule, if the files list them
+    pub atoms: Vec<Atom>,
+    /// Unit system of J and the coordinates
+    pub units: Units,
+}
+
+impl CurrentDensity {
+    /// Reads Jx, Jy and Jz from three Gaussian cube files, in atomic units.
+    ///
+    /// The files have to share a grid with voxel vectors along x, y and z.
+    /// Returns [`Error::InvalidFile`](enum.Error.html#variant.InvalidFile)
+    /// otherwise. The atoms are taken from the file of Jx.
+    pub fn from_cubes<P: AsRef<Path>>(jx: P, jy: P, jz: P) -> Result<Self> {
+        let cube_x = Cube::read(&jx)?;
+        let cube_y = Cube::read(&jy)?;
+        let cube_z = Cube::read(&jz)?;
+
+        for (path, cube) in [(jy.as_ref(), &cube_y), (jz.as_ref(), &cube_z)].iter() {
+            if cube.origin != cube_x.origin
+                || cube.axes != cube_x.axes
+                || cube.data.dim() != cube_x.data.dim()
+            {
+                return Err(Error::InvalidFile {
+                    path: path.display().to_string(),
+                    message: format!("the grid differs from the one of {}", jx.as_ref().display()),
+                });
+            }
+        }
+        let [x_cor, y_cor, z_cor] = cube_x.coordinates().ok_or_else(|| Error::InvalidFile {
+            path: jx.as_ref().display().to_string(),
+            message: "the voxel vectors are not along x, y and z".to_string(),
+        })?;
+
+        Ok(CurrentDensity {
+            jx: cube_x.data,
+            jy: cube_y.data,
+            jz: cube_z.data,
+            x_cor,
+            y_cor,
+            z_cor,
+            atoms: cube_x.atoms,
+            units: Units::Atomic,
+        })
+    }
+
+    /// Reads Jx, Jy and Jz and the coordinates of their grid from NumPy `.npy`
+    /// files, holding 3D and 1D arrays respectively.
+    ///
+    /// `.npy` files carry no units, so the current density is taken to be in
+    /// atomic units unless `units` is changed.
+    pub fn from_npy<P: AsRef<Path>>(j: [P; 3], cor: [P; 3]) -> Result<Self> {
+        let read = |path: &P| -> Result<(ArrayD<f64>, String)> {
+            Ok((read_npy(path)?, path.as_ref().display().to_string()))
+        };
+        Self::from_arrays(
+            [read(&j[0])?, read(&j[1])?, read(&j[2])?],
+            [read(&cor[0])?, read(&cor[1])?, read(&cor[2])?],
+        )
+    }
+
+    /// Reads Jx, Jy and Jz and the coordinates of their grid from the arrays
+    /// `jx`, `jy`, `jz`, `x_cor`, `y_cor` and `z_cor` of a NumPy `.npz` archive,
+    /// such as one written by `numpy.savez(path, jx=jx, ...)`.
+    ///
+    /// Like [`from_npy`](#method.from_npy), the current density is taken to be
+    /// in atomic units.
+    pub fn from_npz<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let names = ["jx", "jy", "jz", "x_cor", "y_cor", "z_cor"];
+        let mut arrays = read_npz(&path, &names)?
+            .into_iter()
+            .zip(&names)
+            .map(|(array, name)| {
+                let name = format!("{}, array '{}'", path.as_ref().display(), name);
+                (array, name)
+            });
+        let mut next = || arrays.next().unwrap();
+        Self::from_arrays([next(), next(), next()], [next(), next(), next()])
+    }
+
+    // Checks that the arrays of J are 3D and the coordinates 1D. The arrays come
+    // with the name of the file they were read from, for errors.
+    fn from_arrays(j: [(ArrayD<f64>, String); 3], cor: [(ArrayD<f64>, String); 3]) -> Result<Self> {
+        let [jx, jy, jz] = j;
+        let [x_cor, y_cor, z_cor] = cor;
+        let grid = |(array, path): (ArrayD<f64>, String)| -> Result<Array3<f64>> {
+            let ndim = array.ndim();
+            array.into_dimensionality().map_err(|_| Error::InvalidFile {
+                path,
+                message: format!("holds a {}D array, expected 3D", ndim),
+            })
+        };
+        let coordinates = |(array, path): (ArrayD<f64>, String)| -> Result<Vec<f64>> {
+            if array.ndim() != 1 {
+                return Err(Error::InvalidFile {
+                    path,
+                    message: format!("holds a {}D array, expected 1D", array.ndim()),
+                });
+            }
+            Ok(array.iter().cloned().collect())
+        };
+
+        Ok(CurrentDensity {
+            jx: grid(jx)?,
+            jy: grid(jy)?,
+            jz: grid(jz)?,
+            x_cor: coordinates(x_cor)?,
+            y_cor: coordinates(y_cor)?,
+            z_cor: coordinates(z_cor)?,
+            atoms: Vec::new(),
+            units: Units::Atomic,
+        })
+    }
+
+    /// Reads the current density written by GIMIC, as `jvec.txt`, with lines of
+    /// `x y z jx jy jz`, or as `jvec.vti`, VTK ImageData in ASCII format. The
+    /// format is chosen by the extension, `.vti` or anything else for text.
+    ///
+    /// GIMIC writes lengths in bohr and J in atomic units. The grid has to be
+    /// along x, y and z, so GIMIC runs with a rotated grid cannot be read.
+    pub fn from_gimic<P: AsRef<Path>>(path: P) -> Result<Self> {
+        gimic::read(path.as_ref())
+    }
+
+    /// Creates a solver for the current density, in its unit system.
+    pub fn solver(&self) -> Result<BiotSavart> {
+        Ok(BiotSavart::new(
+            self.jx.view(),
+            self.jy.view(),
+            self.jz.view(),
+            &self.x_cor,
+            &self.y_cor,
+            &self.z_cor,
+        )?
+        .units(self.units))
+    }
+}
@@ -0,0 +1,124 @@
+use std::error;
+use std::fmt;
+use std::io;
+use std::path::Path;
+use std::result;
+
+use crate::kernel::Backend;
+
+/// Errors in the input of a Biot-Savart calculation.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Error {
+    /// An array does not have the shape required by the grid.
+    ShapeMismatch {
+        name: &'static str,
+        expected: Vec<usize>,
+        found: Vec<usize>,
+    },
+    /// A grid has no points along a dimension.
+    EmptyGrid(&'static str),
+    /// Coordinates are neither strictly increasing nor strictly decreasing.
+    NonMonotonic(&'static str),
+    /// An array contains NaN or infinite values.
+    NonFinite(&'static str),
+    /// The FFT solver was chosen for a grid that is not uniformly spaced.
+    NonUniform,
+    /// The FFT solver was chosen for targets other than the grid of J.
+    FftOffGrid,
+    /// The CPU does not support the instruction set of a forced backend.
+    UnsupportedBackend(Backend),
+    /// The calculation was stopped through its
+    /// [`CancelToken`](struct.CancelToken.html).
+    Cancelled,
+    /// A file could not be read or written.
+    Io(String),
+    /// A file is not in the expected format.
+    InvalidFile { path: String, message: String },
+    /// A half-plane to integrate the current over is degenerate.
+    InvalidPlane(&'static str),
+    /// The opening angle of the tree code is negative or NaN.
+    InvalidTheta(f64),
+}
+
+pub type Result<T> = result::Result<T, Error>;
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::ShapeMismatch {
+                name,
+                expected,
+                found,
+            } => write!(f, "{} has shape {:?}, expected {:?}", name, found, expected),
+            Error::EmptyGrid(name) => write!(f, "{} has no points", name),
+            Error::NonMonotonic(name) => {
+                write!(f, "{} is not strictly increasing or decreasing", name)
+            }
+            Error::NonFinite(name) => write!(f, "{} contains NaN or infinite values", name),
+            Error::NonUniform => write!(
+                f,
+                "the FFT solver requires uniformly spaced x_cor, y_cor and z_cor"
+            ),
+            Error::FftOffGrid => write!(f, "the FFT solver only calculates B on the grid of J"),
+            Error::UnsupportedBackend(backend) => {
+                write!(f, "the CPU does not support the {} backend", backend)
+            }
+            Error::Cancelled => write!(f, "the calculation was cancelled"),
+            Error::Io(message) => write!(f, "{}", message),
+            Error::InvalidFile { path, message } => write!(f, "{}: {}", path, message),
+            Error::InvalidPlane(message) => write!(f, "invalid half-plane, {}", message),
+            Error::InvalidTheta(theta) => write!(
+                f,
+                "the opening angle of the tree code must be at least 0, found {}",
+                theta
+            ),
+        }
+    }
+}
+
+impl error::Error for Error {}
+
+impl Error {
+    pub(crate) fn io(path: &Path, err: io::Error) -> Error {
+        Error::Io(format!("{}: {}", path.display(), err))
+    }
+}
+
+// Checks that the coordinates of a grid dimension are usable.
+pub(crate) fn check_coordinates(name: &'static str, cor: &[f64]) -> Result<()> {
+    if cor.is_empty() {
+        return Err(Error::EmptyGrid(name));
+    }
+    check_finite(name, cor.iter())?;
+
+    let increasing = cor.windows(2).all(|pair| pair[1] > pair[0]);
+    let decreasing = cor.windows(2).all(|pair| pair[1] < pair[0]);
+    if increasing || decreasing {
+        Ok(())
+    } else {
+        Err(Error::NonMonotonic(name))
+    }
+}
+
+pub(crate) fn check_finite<'a, I>(name: &'static str, values: I) -> Result<()>
+where
+    I: IntoIterator<Item = &'a f64>,
+{
+    if values.into_iter().all(|val| val.is_finite()) {
+        Ok(())
+    } else {
+        Err(Error::NonFinite(name))
+    }
+}
+
+pub(crate) fn check_shape(name: &'static str, found: &[usize], expected: &[usize]) -> Result<()> {
+    if found == expected {
+        Ok(())
+    } else {
+        Err(Error::ShapeMismatch {
+            name,
+            expected: expected.to_vec(),
+            found: found.to_vec(),
+        })
+    }
+}
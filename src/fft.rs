@@ -0,0 +1,192 @@
+use ndarray::prelude::*;
+use ndarray::Zip;
+
+use rustfft::num_complex::Complex;
+use rustfft::num_traits::Zero;
+use rustfft::FFTplanner;
+
+use crate::cancel::CancelToken;
+use crate::error::{Error, Result};
+use crate::progress::Tally;
+
+// Relative tolerance on the grid spacing for a grid to count as uniform
+const UNIFORM_TOLERANCE: f64 = 1e-6;
+
+// Stages of `convolve`: transforming J, transforming the kernel, multiplying
+// them, and transforming the product back
+pub(crate) const CONVOLUTION_STAGES: usize = 4;
+
+// Returns the spacing of the coordinates if they are uniformly spaced.
+// A single coordinate has no spacing, and gets spacing 1.
+pub(crate) fn uniform_spacing(cor: &[f64]) -> Option<f64> {
+    if cor.len() < 2 {
+        return Some(1.0);
+    }
+
+    let h = (cor[cor.len() - 1] - cor[0]) / (cor.len() - 1) as f64;
+    if h == 0.0 {
+        return None;
+    }
+    let uniform = cor
+        .windows(2)
+        .all(|pair| ((pair[1] - pair[0]) - h).abs() <= UNIFORM_TOLERANCE * h.abs());
+
+    if uniform {
+        Some(h)
+    } else {
+        None
+    }
+}
+
+// Calculates ∫ J × r / r³ dV at every point of a uniform grid with spacing `h`
+// as a convolution of J with the kernel r / r³.
+//
+// J is zero-padded to twice its size along each dimension, so the circular
+// convolution of the FFT does not wrap around. The kernel is softened by eps2,
+// and is zero at r = 0, like the direct sum that leaves out the point itself.
+//
+// `cancel` is checked before each of the `CONVOLUTION_STAGES` stages, and each
+// one is added to `tally` once it is done.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn convolve(
+    jx: &ArrayView3<f64>,
+    jy: &ArrayView3<f64>,
+    jz: &ArrayView3<f64>,
+    h: [f64; 3],
+    dv: f64,
+    eps2: f64,
+    cancel: &CancelToken,
+    tally: &Tally,
+) -> Result<(Array3<f64>, Array3<f64>, Array3<f64>)> {
+    let (nx, ny, nz) = jx.dim();
+    let padded = (2 * nx, 2 * ny, 2 * nz);
+    let check_cancelled = || {
+        if cancel.is_cancelled() {
+            Err(Error::Cancelled)
+        } else {
+            Ok(())
+        }
+    };
+
+    check_cancelled()?;
+    let jx_hat = transform(pad(jx, padded), false);
+    let jy_hat = transform(pad(jy, padded), false);
+    let jz_hat = transform(pad(jz, padded), false);
+    tally.add();
+
+    check_cancelled()?;
+    let (kx, ky, kz) = kernel((nx, ny, nz), padded, h, eps2);
+    let kx_hat = transform(kx, false);
+    let ky_hat = transform(ky, false);
+    let kz_hat = transform(kz, false);
+    tally.add();
+
+    check_cancelled()?;
+    // The spectra of J are overwritten with the spectra of B = J × K
+    let mut bx_hat = jx_hat;
+    let mut by_hat = jy_hat;
+    let mut bz_hat = jz_hat;
+    Zip::from(&mut bx_hat)
+        .and(&mut by_hat)
+        .and(&mut bz_hat)
+        .and(&kx_hat)
+        .and(&ky_hat)
+        .and(&kz_hat)
+        .par_apply(|bx, by, bz, kx, ky, kz| {
+            let (jx, jy, jz) = (*bx, *by, *bz);
+            *bx = jy * kz - jz * ky;
+            *by = jz * kx - jx * kz;
+            *bz = jx * ky - jy * kx;
+        });
+    tally.add();
+
+    check_cancelled()?;
+    let scale = dv / (padded.0 * padded.1 * padded.2) as f64;
+    let crop = |b_hat: Array3<Complex<f64>>| {
+        transform(b_hat, true)
+            .slice(s![..nx, ..ny, ..nz])
+            .mapv(|b| b.re * scale)
+    };
+
+    let b = (crop(bx_hat), crop(by_hat), crop(bz_hat));
+    tally.add();
+    Ok(b)
+}
+
+fn pad(j: &ArrayView3<f64>, padded: (usize, usize, usize)) -> Array3<Complex<f64>> {
+    let (nx, ny, nz) = j.dim();
+    let mut result = Array3::<Complex<f64>>::zeros(padded);
+    Zip::from(result.slice_mut(s![..nx, ..ny, ..nz]))
+        .and(j)
+        .apply(|result, j| *result = Complex::new(*j, 0.0));
+    result
+}
+
+// Builds the components of r / (r² + eps2)^(3/2) on the padded grid, with
+// negative offsets stored from the end of each dimension.
+fn kernel(
+    n: (usize, usize, usize),
+    padded: (usize, usize, usize),
+    h: [f64; 3],
+    eps2: f64,
+) -> (
+    Array3<Complex<f64>>,
+    Array3<Complex<f64>>,
+    Array3<Complex<f64>>,
+) {
+    let offset = |i: usize, n: usize, p: usize| -> Option<f64> {
+        if i < n {
+            Some(i as f64)
+        } else if i > p - n {
+            Some(i as f64 - p as f64)
+        } else {
+            None
+        }
+    };
+
+    let mut kx = Array3::<Complex<f64>>::zeros(padded);
+    let mut ky = Array3::<Complex<f64>>::zeros(padded);
+    let mut kz = Array3::<Complex<f64>>::zeros(padded);
+
+    Zip::indexed(&mut kx)
+        .and(&mut ky)
+        .and(&mut kz)
+        .par_apply(|idx, kx, ky, kz| {
+            let ox = offset(idx.0, n.0, padded.0);
+            let oy = offset(idx.1, n.1, padded.1);
+            let oz = offset(idx.2, n.2, padded.2);
+            if let (Some(ox), Some(oy), Some(oz)) = (ox, oy, oz) {
+                let rx = ox * h[0];
+                let ry = oy * h[1];
+                let rz = oz * h[2];
+                let r2 = rx * rx + ry * ry + rz * rz;
+                if r2 > 0.0 {
+                    let r3 = (r2 + eps2) * (r2 + eps2).sqrt();
+                    *kx = Complex::new(rx / r3, 0.0);
+                    *ky = Complex::new(ry / r3, 0.0);
+                    *kz = Complex::new(rz / r3, 0.0);
+                }
+            }
+        });
+
+    (kx, ky, kz)
+}
+
+// 3D FFT done as 1D FFTs along each dimension in turn.
+fn transform(mut data: Array3<Complex<f64>>, inverse: bool) -> Array3<Complex<f64>> {
+    let mut planner = FFTplanner::new(inverse);
+
+    for axis in 0..3 {
+        let len = data.len_of(Axis(axis));
+        let fft = planner.plan_fft(len);
+
+        Zip::from(data.lanes_mut(Axis(axis))).par_apply(|mut lane| {
+            let mut input: Vec<Complex<f64>> = lane.iter().cloned().collect();
+            let mut output = vec![Complex::zero(); len];
+            fft.process(&mut input, &mut output);
+            lane.assign(&ArrayView1::from(&output[..]));
+        });
+    }
+
+    data
+}
@@ -0,0 +1,121 @@
+use std::path::{Path, PathBuf};
+
+use ndarray::prelude::*;
+use ndarray::Zip;
+
+use crate::cube::{Atom, Cube};
+use crate::error::{check_coordinates, check_shape, Result};
+use crate::npy::NpzWriter;
+use crate::vtk;
+
+/// Vector field on a rectilinear grid, such as B returned by
+/// [`BiotSavart::field_on_grid`](struct.BiotSavart.html#method.field_on_grid),
+/// together with the coordinates of the grid, for writing it to files.
+pub struct VectorField<'a> {
+    pub x: ArrayView3<'a, f64>,
+    pub y: ArrayView3<'a, f64>,
+    pub z: ArrayView3<'a, f64>,
+    pub x_cor: &'a [f64],
+    pub y_cor: &'a [f64],
+    pub z_cor: &'a [f64],
+}
+
+impl<'a> VectorField<'a> {
+    /// Returns an error if a coordinate array is empty, not strictly monotonic or
+    /// not finite, or if the components do not match the coordinates.
+    pub fn new(
+        x: ArrayView3<'a, f64>,
+        y: ArrayView3<'a, f64>,
+        z: ArrayView3<'a, f64>,
+        x_cor: &'a [f64],
+        y_cor: &'a [f64],
+        z_cor: &'a [f64],
+    ) -> Result<Self> {
+        check_coordinates("x_cor", x_cor)?;
+        check_coordinates("y_cor", y_cor)?;
+        check_coordinates("z_cor", z_cor)?;
+        let shape = [x_cor.len(), y_cor.len(), z_cor.len()];
+        check_shape("x", x.shape(), &shape)?;
+        check_shape("y", y.shape(), &shape)?;
+        check_shape("z", z.shape(), &shape)?;
+        Ok(VectorField {
+            x,
+            y,
+            z,
+            x_cor,
+            y_cor,
+            z_cor,
+        })
+    }
+
+    /// Returns the length of the vectors.
+    pub fn norm(&self) -> Array3<f64> {
+        let mut norm = Array3::zeros(self.x.dim());
+        Zip::from(&mut norm)
+            .and(&self.x)
+            .and(&self.y)
+            .and(&self.z)
+            .apply(|norm, x, y, z| *norm = (x * x + y * y + z * z).sqrt());
+        norm
+    }
+
+    /// Writes the components and the length of the vectors to four Gaussian cube
+    /// files, `<stem>_x.cube`, `<stem>_y.cube`, `<stem>_z.cube` and
+    /// `<stem>_norm.cube`, with `name` in their comments.
+    ///
+    /// Cube files are in bohr, so the coordinates have to be in bohr as well, and
+    /// uniformly spaced. Returns the paths of the files.
+    pub fn write_cubes<P: AsRef<Path>>(
+        &self,
+        stem: P,
+        name: &str,
+        atoms: &[Atom],
+    ) -> Result<Vec<PathBuf>> {
+        let cor = [self.x_cor, self.y_cor, self.z_cor];
+        let components = vec![
+            ("x", self.x.to_owned()),
+            ("y", self.y.to_owned()),
+            ("z", self.z.to_owned()),
+            ("norm", self.norm()),
+        ];
+
+        let mut paths = Vec::new();
+        for (suffix, data) in components {
+            let mut path = stem.as_ref().as_os_str().to_owned();
+            path.push(format!("_{}.cube", suffix));
+            let path = PathBuf::from(path);
+
+            let mut cube = Cube::new(data, cor, atoms.to_vec())?;
+            cube.comments = [format!("{} {}", name, suffix), "biot_savart".to_string()];
+            cube.write(&path)?;
+            paths.push(path);
+        }
+        Ok(paths)
+    }
+
+    /// Writes the field as a VTK RectilinearGrid file (.vtr), with the vectors
+    /// as the point data `name` and their length as `<name>_norm`.
+    pub fn write_vtr<P: AsRef<Path>>(&self, path: P, name: &str) -> Result<()> {
+        vtk::write(path.as_ref(), self, name, vtk::Grid::Rectilinear)
+    }
+
+    /// Writes the field as a VTK ImageData file (.vti), like
+    /// [`write_vtr`](#method.write_vtr). ImageData requires uniformly spaced
+    /// coordinates, so this returns
+    /// [`Error::NonUniform`](enum.Error.html#variant.NonUniform) otherwise.
+    pub fn write_vti<P: AsRef<Path>>(&self, path: P, name: &str) -> Result<()> {
+        vtk::write(path.as_ref(), self, name, vtk::Grid::Image)
+    }
+
+    /// Adds the components of the field to a `.npz` archive as the arrays
+    /// `<name>x`, `<name>y` and `<name>z`, e.g. `Bx`, together with the
+    /// coordinates of the grid as `x_cor`, `y_cor` and `z_cor`.
+    pub fn add_to_npz(&self, npz: &mut NpzWriter, name: &str) -> Result<()> {
+        npz.add_array(&format!("{}x", name), self.x.view())?;
+        npz.add_array(&format!("{}y", name), self.y.view())?;
+        npz.add_array(&format!("{}z", name), self.z.view())?;
+        npz.add_array("x_cor", ArrayView::from(self.x_cor))?;
+        npz.add_array("y_cor", ArrayView::from(self.y_cor))?;
+        npz.add_array("z_cor", ArrayView::from(self.z_cor))
+    }
+}
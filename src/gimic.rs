@@ -0,0 +1,230 @@
+use std::cmp::Ordering;
+use std::fs;
+use std::path::Path;
+
+use ndarray::prelude::*;
+
+use crate::density::CurrentDensity;
+use crate::error::{Error, Result};
+use crate::units::Units;
+
+// Reads the current density written by GIMIC, in atomic units with lengths in
+// bohr. Files ending in .vti are read as VTK ImageData, others as text with a
+// point per line.
+pub(crate) fn read(path: &Path) -> Result<CurrentDensity> {
+    let contents = fs::read_to_string(path).map_err(|err| Error::io(path, err))?;
+    let invalid = |message: String| Error::InvalidFile {
+        path: path.display().to_string(),
+        message,
+    };
+    let (cor, j) = if path.extension().map_or(false, |ext| ext == "vti") {
+        parse_vti(&contents).map_err(invalid)?
+    } else {
+        parse_text(&contents).map_err(invalid)?
+    };
+    let [x_cor, y_cor, z_cor] = cor;
+    let [jx, jy, jz] = j;
+
+    Ok(CurrentDensity {
+        jx,
+        jy,
+        jz,
+        x_cor,
+        y_cor,
+        z_cor,
+        atoms: Vec::new(),
+        units: Units::Atomic,
+    })
+}
+
+type Grid = ([Vec<f64>; 3], [Array3<f64>; 3]);
+
+// Parses lines of `x y z jx jy jz`, as in jvec.txt. The points may come in any
+// order, but have to make up a rectilinear grid along x, y and z.
+fn parse_text(contents: &str) -> std::result::Result<Grid, String> {
+    let mut points = Vec::new();
+    for (n, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let values: Vec<f64> = line
+            .split_whitespace()
+            .map(|value| value.parse())
+            .collect::<std::result::Result<_, _>>()
+            .map_err(|_| format!("line {} is not a list of numbers", n + 1))?;
+        if values.len() != 6 {
+            return Err(format!(
+                "line {} has {} values, expected x y z jx jy jz",
+                n + 1,
+                values.len()
+            ));
+        }
+        points.push(values);
+    }
+    if points.is_empty() {
+        return Err("the file holds no points".to_string());
+    }
+
+    let axis = |a: usize| distinct(points.iter().map(|point| point[a]));
+    let cor = [axis(0), axis(1), axis(2)];
+    let shape = (cor[0].0.len(), cor[1].0.len(), cor[2].0.len());
+    if shape.0 * shape.1 * shape.2 != points.len() {
+        return Err("the points do not make up a grid along x, y and z".to_string());
+    }
+
+    let mut j = [
+        Array3::from_elem(shape, f64::NAN),
+        Array3::from_elem(shape, f64::NAN),
+        Array3::from_elem(shape, f64::NAN),
+    ];
+    for point in &points {
+        let index = |a: usize| {
+            let (cor, tol) = &cor[a];
+            cor.binary_search_by(|c| {
+                if *c < point[a] - tol {
+                    Ordering::Less
+                } else if *c > point[a] + tol {
+                    Ordering::Greater
+                } else {
+                    Ordering::Equal
+                }
+            })
+            .unwrap()
+        };
+        let index = (index(0), index(1), index(2));
+        if !j[0][index].is_nan() {
+            return Err(format!(
+                "the point ({}, {}, {}) is listed twice",
+                point[0], point[1], point[2]
+            ));
+        }
+        for (a, j) in j.iter_mut().enumerate() {
+            j[index] = point[3 + a];
+        }
+    }
+
+    let [(x_cor, _), (y_cor, _), (z_cor, _)] = cor;
+    Ok(([x_cor, y_cor, z_cor], j))
+}
+
+// Returns the sorted distinct values, merging those closer than a tolerance
+// relative to their range, and the tolerance.
+fn distinct<I: Iterator<Item = f64>>(values: I) -> (Vec<f64>, f64) {
+    let mut values: Vec<f64> = values.collect();
+    values.sort_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+    let range = values[values.len() - 1] - values[0];
+    let tol = 1e-6 * range.max(1.0);
+
+    let mut distinct: Vec<f64> = Vec::new();
+    for value in values {
+        match distinct.last() {
+            Some(last) if value - last <= tol => {}
+            _ => distinct.push(value),
+        }
+    }
+    (distinct, tol)
+}
+
+// Parses a VTK ImageData file in ASCII format, as jvec.vti, taking J from the
+// first point data array with three components.
+fn parse_vti(contents: &str) -> std::result::Result<Grid, String> {
+    let image = tag(contents, "ImageData").ok_or("the file holds no ImageData")?;
+    let numbers = |name: &str, len: usize| -> std::result::Result<Vec<f64>, String> {
+        let values: Vec<f64> = attribute(image, name)
+            .ok_or_else(|| format!("ImageData has no {}", name))?
+            .split_whitespace()
+            .map(|value| value.parse().map_err(|_| format!("invalid {}", name)))
+            .collect::<std::result::Result<_, _>>()?;
+        if values.len() != len {
+            return Err(format!(
+                "{} has {} values, expected {}",
+                name,
+                values.len(),
+                len
+            ));
+        }
+        Ok(values)
+    };
+    let extent = numbers("WholeExtent", 6)?;
+    let origin = numbers("Origin", 3)?;
+    let spacing = numbers("Spacing", 3)?;
+
+    let axis = |a: usize| -> Vec<f64> {
+        let (start, stop) = (extent[2 * a] as i64, extent[2 * a + 1] as i64);
+        (start..=stop)
+            .map(|i| origin[a] + i as f64 * spacing[a])
+            .collect()
+    };
+    let cor = [axis(0), axis(1), axis(2)];
+    let shape = (cor[0].len(), cor[1].len(), cor[2].len());
+    if shape.0 * shape.1 * shape.2 == 0 {
+        return Err("WholeExtent is empty".to_string());
+    }
+
+    let point_data = &contents[contents
+        .find("<PointData")
+        .ok_or("the file holds no PointData")?..];
+    let mut rest = point_data;
+    let (array, values) = loop {
+        let start = rest
+            .find("<DataArray")
+            .ok_or("PointData has no array with 3 components")?;
+        let array = tag(&rest[start..], "DataArray").unwrap();
+        let body = &rest[start + array.len()..];
+        let end = body
+            .find("</DataArray>")
+            .ok_or("a DataArray is not closed")?;
+        if attribute(array, "NumberOfComponents") == Some("3") {
+            break (array, &body[..end]);
+        }
+        rest = &body[end..];
+    };
+    match attribute(array, "format") {
+        Some("ascii") | None => {}
+        Some(format) => {
+            return Err(format!(
+                "J is stored in {} format, only ascii can be read",
+                format
+            ))
+        }
+    }
+
+    let values: Vec<f64> = values
+        .split_whitespace()
+        .map(|value| value.parse())
+        .collect::<std::result::Result<_, _>>()
+        .map_err(|_| "J holds invalid numbers".to_string())?;
+    let len = shape.0 * shape.1 * shape.2;
+    if values.len() != 3 * len {
+        return Err(format!(
+            "J has {} values, expected 3 per point of the {}x{}x{} grid",
+            values.len(),
+            shape.0,
+            shape.1,
+            shape.2
+        ));
+    }
+
+    // the points run with x fastest
+    let component = |a: usize| {
+        Array3::from_shape_fn(shape, |(i, j, k)| {
+            values[3 * (i + shape.0 * (j + shape.1 * k)) + a]
+        })
+    };
+    Ok((cor, [component(0), component(1), component(2)]))
+}
+
+// Returns the start tag `<name ...>` of the first element `name`.
+fn tag<'c>(contents: &'c str, name: &str) -> Option<&'c str> {
+    let start = contents.find(&format!("<{}", name))?;
+    let end = contents[start..].find('>')? + 1;
+    Some(&contents[start..start + end])
+}
+
+// Returns the value of the attribute `name` of a start tag.
+fn attribute<'t>(tag: &'t str, name: &str) -> Option<&'t str> {
+    let start = tag.find(&format!(" {}=\"", name))? + name.len() + 3;
+    let end = tag[start..].find('"')?;
+    Some(&tag[start..start + end])
+}
@@ -0,0 +1,89 @@
+use ndarray::prelude::*;
+
+// Width of the grid cell around each coordinate, used as volume element when
+// integrating over the grid. The cell of a point reaches halfway to its
+// neighbours, and the outermost cells are symmetric around their point, so a
+// uniform grid with spacing h has width h everywhere.
+//
+// A dimension with a single point is not integrated over, and gets width 1.
+pub(crate) fn cell_widths(cor: &[f64]) -> Vec<f64> {
+    let n = cor.len();
+    if n < 2 {
+        return vec![1.0; n];
+    }
+
+    (0..n)
+        .map(|i| {
+            if i == 0 {
+                (cor[1] - cor[0]).abs()
+            } else if i == n - 1 {
+                (cor[n - 1] - cor[n - 2]).abs()
+            } else {
+                (cor[i + 1] - cor[i - 1]).abs() * 0.5
+            }
+        })
+        .collect()
+}
+
+// Interpolates the vector field `v` on a rectilinear grid trilinearly at
+// `point`. Returns None outside of the grid. A dimension with a single point
+// only holds the plane through it.
+pub(crate) fn trilinear(
+    v: [&ArrayView3<f64>; 3],
+    cor: [&[f64]; 3],
+    point: [f64; 3],
+) -> Option<[f64; 3]> {
+    let (i, wx) = locate(cor[0], point[0])?;
+    let (j, wy) = locate(cor[1], point[1])?;
+    let (k, wz) = locate(cor[2], point[2])?;
+    let next = |index: usize, cor: &[f64]| (index + 1).min(cor.len() - 1);
+    let (i1, j1, k1) = (next(i, cor[0]), next(j, cor[1]), next(k, cor[2]));
+
+    let mut result = [0.0; 3];
+    for &(a, wa) in &[(i, 1.0 - wx), (i1, wx)] {
+        for &(b, wb) in &[(j, 1.0 - wy), (j1, wy)] {
+            for &(c, wc) in &[(k, 1.0 - wz), (k1, wz)] {
+                let w = wa * wb * wc;
+                for (result, v) in result.iter_mut().zip(&v) {
+                    *result += w * v[[a, b, c]];
+                }
+            }
+        }
+    }
+    Some(result)
+}
+
+// Returns the index i of the cell from cor[i] to cor[i + 1] holding `value`,
+// and the weight w of cor[i + 1] in value = (1 - w) cor[i] + w cor[i + 1].
+// The coordinates may be increasing or decreasing.
+fn locate(cor: &[f64], value: f64) -> Option<(usize, f64)> {
+    let n = cor.len();
+    if n == 1 {
+        let tol = 1e-10 * cor[0].abs().max(1.0);
+        return if (value - cor[0]).abs() <= tol {
+            Some((0, 0.0))
+        } else {
+            None
+        };
+    }
+    let (first, last) = (cor[0], cor[n - 1]);
+    if value < first.min(last) || value > first.max(last) {
+        return None;
+    }
+
+    let increasing = last > first;
+    let found = cor.binary_search_by(|c| {
+        let order = c.partial_cmp(&value).unwrap();
+        if increasing {
+            order
+        } else {
+            order.reverse()
+        }
+    });
+    let i = match found {
+        Ok(i) => i,
+        Err(i) => i.saturating_sub(1),
+    }
+    .min(n - 2);
+    Some((i, (value - cor[i]) / (cor[i + 1] - cor[i])))
+}
@@ -0,0 +1,234 @@
+use std::fmt;
+use std::str::FromStr;
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+use simdeez::{avx2::*, scalar::*, sse2::*, sse41::*, *};
+
+/// Instruction set used by the direct sum.
+///
+/// `Auto` picks the widest instruction set supported by the CPU at runtime.
+/// The others force a specific one, which is useful to reproduce results
+/// across machines, as the order of the floating point additions depends on
+/// the vector width. AVX2 is the widest instruction set provided by simdeez,
+/// so AVX-512 CPUs use AVX2 as well.
+///
+/// The vector backends are x86 and x86-64 only. simdeez has no NEON support,
+/// so on other architectures, such as ARM, the direct sum is scalar and only
+/// `Auto` and `Scalar` are supported.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Backend {
+    /// The widest backend the CPU supports, as returned by
+    /// [`detect`](#method.detect)
+    Auto,
+    /// No vector instructions, one value at a time
+    Scalar,
+    /// 2 values at a time
+    Sse2,
+    /// 2 values at a time
+    Sse41,
+    /// 4 values at a time
+    Avx2,
+}
+
+impl Backend {
+    /// Returns the backend `Auto` selects on this CPU.
+    pub fn detect() -> Self {
+        [Backend::Avx2, Backend::Sse41, Backend::Sse2]
+            .iter()
+            .cloned()
+            .find(|backend| backend.is_supported())
+            .unwrap_or(Backend::Scalar)
+    }
+
+    /// Returns true if the CPU supports the instruction set of the backend.
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    pub fn is_supported(self) -> bool {
+        match self {
+            Backend::Auto | Backend::Scalar => true,
+            Backend::Sse2 => is_x86_feature_detected!("sse2"),
+            Backend::Sse41 => is_x86_feature_detected!("sse4.1"),
+            Backend::Avx2 => is_x86_feature_detected!("avx2"),
+        }
+    }
+
+    /// Returns true if the CPU supports the instruction set of the backend.
+    #[cfg(not(any(target_arch = "x86", target_arch = "x86_64")))]
+    pub fn is_supported(self) -> bool {
+        match self {
+            Backend::Auto | Backend::Scalar => true,
+            Backend::Sse2 | Backend::Sse41 | Backend::Avx2 => false,
+        }
+    }
+}
+
+impl Default for Backend {
+    fn default() -> Self {
+        Backend::Auto
+    }
+}
+
+impl FromStr for Backend {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "auto" => Ok(Backend::Auto),
+            "scalar" => Ok(Backend::Scalar),
+            "sse2" => Ok(Backend::Sse2),
+            "sse41" | "sse4.1" => Ok(Backend::Sse41),
+            "avx2" => Ok(Backend::Avx2),
+            _ => Err(format!(
+                "unknown backend '{}', expected 'auto', 'scalar', 'sse2', 'sse41' or 'avx2'",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Backend {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Backend::Auto => write!(f, "auto"),
+            Backend::Scalar => write!(f, "scalar"),
+            Backend::Sse2 => write!(f, "sse2"),
+            Backend::Sse41 => write!(f, "sse41"),
+            Backend::Avx2 => write!(f, "avx2"),
+        }
+    }
+}
+
+// Signature of the instantiations of `sum`
+pub(crate) type Kernel =
+    unsafe fn(&[f64; 3], f64, f64, &[f64], f64, &[f64], f64, &[f64], &[f64], &[f64]) -> [f64; 3];
+
+// Returns the instantiation of `sum` for the backend, which has to be supported
+// by the CPU.
+pub(crate) fn kernel(backend: Backend) -> Kernel {
+    match backend {
+        Backend::Auto => kernel(Backend::detect()),
+        Backend::Scalar => sum_scalar,
+        #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+        Backend::Sse2 => sum_sse2,
+        #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+        Backend::Sse41 => sum_sse41,
+        #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+        Backend::Avx2 => sum_avx2,
+        #[cfg(not(any(target_arch = "x86", target_arch = "x86_64")))]
+        backend => unreachable!("the {} backend is x86 only", backend),
+    }
+}
+
+// Returns Σ J × r / r³ dV over a line of grid points along z at (x, y), with
+// dV = w dz and r the offset of the target `b_r` from each point.
+//
+// The terms are accumulated in vector registers, so nothing is allocated. The
+// points left over after the last full vector are added one at a time, so the
+// slices can have any length, as long as it is the same for all of them.
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+simd_runtime_generate!(
+    pub fn sum(
+        b_r: &[f64; 3],
+        x: f64,
+        y: f64,
+        z: &[f64],
+        w: f64,
+        dz: &[f64],
+        eps2: f64,
+        jx: &[f64],
+        jy: &[f64],
+        jz: &[f64],
+    ) -> [f64; 3] {
+        let len = z.len();
+        assert!(dz.len() == len && jx.len() == len && jy.len() == len && jz.len() == len);
+
+        // rx and ry are the same for every point of the line
+        let rx = S::set1_pd(b_r[0] - x);
+        let ry = S::set1_pd(b_r[1] - y);
+        let brz = S::set1_pd(b_r[2]);
+        let w_v = S::set1_pd(w);
+        let eps2_v = S::set1_pd(eps2);
+        let zero = S::setzero_pd();
+
+        let mut acc_x = S::setzero_pd();
+        let mut acc_y = S::setzero_pd();
+        let mut acc_z = S::setzero_pd();
+
+        let full = len - len % S::VF64_WIDTH;
+        for i in (0..full).step_by(S::VF64_WIDTH) {
+            let rz = brz - S::loadu_pd(&z[i]);
+            let jx = S::loadu_pd(&jx[i]);
+            let jy = S::loadu_pd(&jy[i]);
+            let jz = S::loadu_pd(&jz[i]);
+            let dv = w_v * S::loadu_pd(&dz[i]);
+
+            let r2 = (rx * rx) + (ry * ry) + (rz * rz) + eps2_v;
+            let distance = S::sqrt_pd(r2);
+            // a source at the target itself is left out, instead of dividing by zero
+            let scale = S::blendv_pd(
+                zero,
+                dv / (distance * distance * distance),
+                S::cmpneq_pd(r2, zero),
+            );
+
+            acc_x += (jy * rz - ry * jz) * scale;
+            acc_y += (rx * jz - jx * rz) * scale;
+            acc_z += (jx * ry - rx * jy) * scale;
+        }
+
+        let mut result = [
+            S::horizontal_add_pd(acc_x),
+            S::horizontal_add_pd(acc_y),
+            S::horizontal_add_pd(acc_z),
+        ];
+
+        let (rx, ry) = (b_r[0] - x, b_r[1] - y);
+        for i in full..len {
+            let rz = b_r[2] - z[i];
+            let r2 = rx * rx + ry * ry + rz * rz + eps2;
+            if r2 != 0.0 {
+                let scale = w * dz[i] / (r2 * r2.sqrt());
+                result[0] += (jy[i] * rz - ry * jz[i]) * scale;
+                result[1] += (rx * jz[i] - jx[i] * rz) * scale;
+                result[2] += (jx[i] * ry - rx * jy[i]) * scale;
+            }
+        }
+
+        result
+    }
+);
+
+// The same sum one point at a time, for architectures without the vector
+// instructions of simdeez.
+#[cfg(not(any(target_arch = "x86", target_arch = "x86_64")))]
+#[allow(clippy::too_many_arguments)]
+unsafe fn sum_scalar(
+    b_r: &[f64; 3],
+    x: f64,
+    y: f64,
+    z: &[f64],
+    w: f64,
+    dz: &[f64],
+    eps2: f64,
+    jx: &[f64],
+    jy: &[f64],
+    jz: &[f64],
+) -> [f64; 3] {
+    let len = z.len();
+    assert!(dz.len() == len && jx.len() == len && jy.len() == len && jz.len() == len);
+
+    let mut result = [0f64; 3];
+    let (rx, ry) = (b_r[0] - x, b_r[1] - y);
+    let points = z.iter().zip(dz).zip(jx.iter().zip(jy).zip(jz));
+    for ((z, dz), ((jx, jy), jz)) in points {
+        let rz = b_r[2] - z;
+        let r2 = rx * rx + ry * ry + rz * rz + eps2;
+        if r2 != 0.0 {
+            let scale = w * dz / (r2 * r2.sqrt());
+            result[0] += (jy * rz - ry * jz) * scale;
+            result[1] += (rx * jz - jx * rz) * scale;
+            result[2] += (jx * ry - rx * jy) * scale;
+        }
+    }
+
+    result
+}
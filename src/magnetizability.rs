@@ -0,0 +1,69 @@
+use crate::solver::BiotSavart;
+use crate::tensor::{symmetric_eigen, symmetric_part};
+
+/// Magnetizability tensor, the magnetic moment induced per unit external field.
+///
+/// `tensor[a][b]` is ξ_ab = m_a / B_0,b, the magnetization along `a` of the
+/// current density induced by a unit field along `b`, around a center. It is
+/// in the units of the magnetization per unit field: a.u. (e² a0² / m_e) for
+/// atomic units, J/T² for SI and erg/G² for Gaussian units. Diamagnetic
+/// molecules have a negative isotropic magnetizability.
+///
+/// With current densities from an incomplete basis set, the tensor depends on
+/// the center, which is kept alongside it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MagnetizabilityTensor {
+    pub tensor: [[f64; 3]; 3],
+    /// Center the magnetizations were calculated around
+    pub center: [f64; 3],
+}
+
+impl MagnetizabilityTensor {
+    /// Calculates the tensor from the solvers of the current densities induced
+    /// by unit external fields along x, y and z, with
+    /// [`BiotSavart::magnetization`](struct.BiotSavart.html#method.magnetization)
+    /// around `center`.
+    ///
+    /// The current densities have to be the response to a field of 1 in the
+    /// units of the solvers, as for [`Shielding`](struct.Shielding.html).
+    pub fn new(responses: [&BiotSavart; 3], center: [f64; 3]) -> Self {
+        let mut tensor = [[0.0; 3]; 3];
+        for (b, response) in responses.iter().enumerate() {
+            let m = response.magnetization(center);
+            for (row, m) in tensor.iter_mut().zip(&m) {
+                row[b] = *m;
+            }
+        }
+        MagnetizabilityTensor { tensor, center }
+    }
+
+    /// Returns ξ_iso, a third of the trace.
+    pub fn isotropic(&self) -> f64 {
+        (self.tensor[0][0] + self.tensor[1][1] + self.tensor[2][2]) / 3.0
+    }
+
+    /// Returns the anisotropic part, the symmetric part of the tensor minus
+    /// ξ_iso times the identity, which is traceless.
+    pub fn anisotropic_part(&self) -> [[f64; 3]; 3] {
+        let mut part = symmetric_part(self.tensor);
+        let isotropic = self.isotropic();
+        for (a, row) in part.iter_mut().enumerate() {
+            row[a] -= isotropic;
+        }
+        part
+    }
+
+    /// Returns the anisotropy, Δξ = ξ_33 - (ξ_11 + ξ_22) / 2, of the principal
+    /// components in ascending order.
+    pub fn anisotropy(&self) -> f64 {
+        let ([x11, x22, x33], _) = self.eigen();
+        x33 - 0.5 * (x11 + x22)
+    }
+
+    /// Returns the principal components ξ_11 ≤ ξ_22 ≤ ξ_33, the eigenvalues of
+    /// the symmetric part of the tensor, and the principal axes, the
+    /// corresponding unit eigenvectors, as rows.
+    pub fn eigen(&self) -> ([f64; 3], [[f64; 3]; 3]) {
+        symmetric_eigen(symmetric_part(self.tensor))
+    }
+}
@@ -0,0 +1,105 @@
+use ndarray::prelude::*;
+use ndarray::Zip;
+
+/// Magnetic multipole moments of a current density around a center, as returned
+/// by [`BiotSavart::multipoles`](struct.BiotSavart.html#method.multipoles).
+///
+/// With r measured from `center`, and k the prefactor of the magnetization,
+/// 1/2 for SI and atomic units and 1/2c for Gaussian units, the moments are
+///
+/// - the dipole, m = k ∫ r × J dV, the magnetization,
+/// - the quadrupole, Q_ab = 2k/3 ∫ [(r × J)_a r_b + r_a (r × J)_b] dV, which is
+///   symmetric and traceless,
+/// - the toroidal dipole, or anapole, T = k/5 ∫ [(r · J) r - 2 r² J] dV.
+///
+/// Q and T are in the units of m times a length, e.g. A m³ for SI. They matter
+/// where m vanishes, such as for currents circulating around a ring.
+///
+/// A moment depends on the center unless the lower ones vanish. For a
+/// divergence-free J that vanishes at the edges of the grid, moving the center
+/// by d changes m by -k d × I, with the net current I = ∫ J dV, and changes Q
+/// and T by terms linear in m and quadratic in I. So m is independent of the
+/// center if I vanishes, and Q and T if m does too.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Multipoles {
+    /// Center the moments are calculated around
+    pub center: [f64; 3],
+    /// I = ∫ J dV, in the units of J times volume, e.g. A m for SI
+    pub net_current: [f64; 3],
+    pub dipole: [f64; 3],
+    pub quadrupole: [[f64; 3]; 3],
+    pub toroidal: [f64; 3],
+}
+
+// Sums the moments over the grid points of J, each weighted by the volume of
+// its cell. `prefactor` is k of the magnetization, and `sign` the factor turning
+// J into the electric current density, which both include.
+pub(crate) fn multipoles(
+    j: [&ArrayView3<f64>; 3],
+    cor: [&[f64]; 3],
+    widths: [&[f64]; 3],
+    center: [f64; 3],
+    prefactor: f64,
+    sign: f64,
+) -> Multipoles {
+    let mut net_current = [0.0; 3];
+    let mut dipole = [0.0; 3];
+    let mut quadrupole = [[0.0; 3]; 3];
+    let mut toroidal = [0.0; 3];
+
+    Zip::indexed(j[0])
+        .and(j[1])
+        .and(j[2])
+        .apply(|(i, k, l), &jx, &jy, &jz| {
+            let dv = widths[0][i] * widths[1][k] * widths[2][l];
+            let r = [
+                cor[0][i] - center[0],
+                cor[1][k] - center[1],
+                cor[2][l] - center[2],
+            ];
+            // J dV, the current of the cell
+            let current = [jx * dv, jy * dv, jz * dv];
+            let r_cross_j = [
+                r[1] * current[2] - r[2] * current[1],
+                r[2] * current[0] - r[0] * current[2],
+                r[0] * current[1] - r[1] * current[0],
+            ];
+            let r_dot_j = r[0] * current[0] + r[1] * current[1] + r[2] * current[2];
+            let r2 = r[0] * r[0] + r[1] * r[1] + r[2] * r[2];
+
+            accumulate(&mut net_current, current);
+            accumulate(&mut dipole, r_cross_j);
+            for (a, row) in quadrupole.iter_mut().enumerate() {
+                for (b, q) in row.iter_mut().enumerate() {
+                    *q += r_cross_j[a] * r[b] + r[a] * r_cross_j[b];
+                }
+            }
+            accumulate(
+                &mut toroidal,
+                [
+                    r_dot_j * r[0] - 2.0 * r2 * current[0],
+                    r_dot_j * r[1] - 2.0 * r2 * current[1],
+                    r_dot_j * r[2] - 2.0 * r2 * current[2],
+                ],
+            );
+        });
+
+    let scale = |v: [f64; 3], s: f64| [v[0] * s, v[1] * s, v[2] * s];
+    Multipoles {
+        center,
+        net_current: scale(net_current, sign),
+        dipole: scale(dipole, prefactor),
+        quadrupole: [
+            scale(quadrupole[0], 2.0 / 3.0 * prefactor),
+            scale(quadrupole[1], 2.0 / 3.0 * prefactor),
+            scale(quadrupole[2], 2.0 / 3.0 * prefactor),
+        ],
+        toroidal: scale(toroidal, prefactor / 5.0),
+    }
+}
+
+fn accumulate(total: &mut [f64; 3], value: [f64; 3]) {
+    for (total, value) in total.iter_mut().zip(&value) {
+        *total += value;
+    }
+}
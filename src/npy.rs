@@ -0,0 +1,284 @@
+use std::convert::TryInto;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::iter;
+use std::path::{Path, PathBuf};
+
+use ndarray::prelude::*;
+use ndarray::IxDyn;
+use zip::result::ZipError;
+use zip::write::FileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+use crate::error::{Error, Result};
+
+const MAGIC: &[u8] = b"\x93NUMPY";
+
+/// Reads a NumPy `.npy` file holding a float64 or float32 array, in C or
+/// Fortran order.
+///
+/// Returns [`Error::Io`](enum.Error.html#variant.Io) if the file cannot be
+/// read, and [`Error::InvalidFile`](enum.Error.html#variant.InvalidFile) if it
+/// is not a `.npy` file or holds another type.
+pub fn read_npy<P: AsRef<Path>>(path: P) -> Result<ArrayD<f64>> {
+    let path = path.as_ref();
+    let file = File::open(path).map_err(|err| Error::io(path, err))?;
+    parse(BufReader::new(file), &path.display().to_string())
+}
+
+/// Reads the arrays `names` from a NumPy `.npz` archive, as written by
+/// `numpy.savez` or `numpy.savez_compressed`.
+///
+/// Returns [`Error::InvalidFile`](enum.Error.html#variant.InvalidFile) if the
+/// archive lacks one of the arrays, or if it is not a float64 or float32 array.
+pub fn read_npz<P: AsRef<Path>>(path: P, names: &[&str]) -> Result<Vec<ArrayD<f64>>> {
+    let path = path.as_ref();
+    let file = File::open(path).map_err(|err| Error::io(path, err))?;
+    let mut archive = ZipArchive::new(BufReader::new(file)).map_err(|err| zip_error(path, err))?;
+    names
+        .iter()
+        .map(|name| {
+            let entry = archive
+                .by_name(&format!("{}.npy", name))
+                .map_err(|err| match err {
+                    ZipError::FileNotFound => Error::InvalidFile {
+                        path: path.display().to_string(),
+                        message: format!("has no array '{}'", name),
+                    },
+                    err => zip_error(path, err),
+                })?;
+            parse(entry, &format!("{}, array '{}'", path.display(), name))
+        })
+        .collect()
+}
+
+/// Writes an array to a NumPy `.npy` file, as float64 in C order.
+pub fn write_npy<P, D>(path: P, array: ArrayView<f64, D>) -> Result<()>
+where
+    P: AsRef<Path>,
+    D: Dimension,
+{
+    let path = path.as_ref();
+    File::create(path)
+        .and_then(|file| {
+            let mut w = BufWriter::new(file);
+            write_array(&mut w, array)?;
+            w.flush()
+        })
+        .map_err(|err| Error::io(path, err))
+}
+
+/// Writer of a NumPy `.npz` archive, which `numpy.load` reads as a dict of
+/// arrays.
+///
+/// Metadata are stored as 0-dimensional string arrays, which Python turns into
+/// `str` through `str(npz["units"])`. The archive is complete once
+/// [`finish`](#method.finish) returns.
+pub struct NpzWriter {
+    zip: ZipWriter<BufWriter<File>>,
+    path: PathBuf,
+}
+
+impl NpzWriter {
+    /// Creates the archive, overwriting an existing file.
+    pub fn create<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let file = File::create(&path).map_err(|err| Error::io(&path, err))?;
+        Ok(NpzWriter {
+            zip: ZipWriter::new(BufWriter::new(file)),
+            path,
+        })
+    }
+
+    /// Adds an array as float64 in C order.
+    pub fn add_array<D: Dimension>(&mut self, name: &str, array: ArrayView<f64, D>) -> Result<()> {
+        self.start(name)?;
+        write_array(&mut self.zip, array).map_err(|err| Error::io(&self.path, err))
+    }
+
+    /// Adds a string.
+    pub fn add_str(&mut self, name: &str, value: &str) -> Result<()> {
+        self.start(name)?;
+        write_str(&mut self.zip, value).map_err(|err| Error::io(&self.path, err))
+    }
+
+    /// Writes the directory of the archive and closes the file.
+    pub fn finish(mut self) -> Result<()> {
+        let path = self.path;
+        self.zip
+            .finish()
+            .map_err(|err| zip_error(&path, err))?
+            .flush()
+            .map_err(|err| Error::io(&path, err))
+    }
+
+    fn start(&mut self, name: &str) -> Result<()> {
+        let options = FileOptions::default().compression_method(CompressionMethod::Stored);
+        self.zip
+            .start_file(format!("{}.npy", name), options)
+            .map_err(|err| zip_error(&self.path, err))
+    }
+}
+
+fn zip_error(path: &Path, err: ZipError) -> Error {
+    match err {
+        ZipError::Io(err) => Error::io(path, err),
+        err => Error::InvalidFile {
+            path: path.display().to_string(),
+            message: format!("not a .npz file, {}", err),
+        },
+    }
+}
+
+// Writes an array in .npy format.
+fn write_array<W: Write, D: Dimension>(w: &mut W, array: ArrayView<f64, D>) -> io::Result<()> {
+    write_header(w, "<f8", array.shape())?;
+    // iter runs in logical, C order, whatever the memory layout
+    for value in array.iter() {
+        w.write_all(&value.to_le_bytes())?;
+    }
+    Ok(())
+}
+
+// Writes a string in .npy format, as a 0-dimensional array of UTF-32 code points.
+fn write_str<W: Write>(w: &mut W, value: &str) -> io::Result<()> {
+    let len = value.chars().count().max(1);
+    write_header(w, &format!("<U{}", len), &[])?;
+    let chars = value.chars().map(u32::from).chain(iter::repeat(0));
+    for c in chars.take(len) {
+        w.write_all(&c.to_le_bytes())?;
+    }
+    Ok(())
+}
+
+// Writes the preamble and the header of a version 1.0 .npy file.
+fn write_header<W: Write>(w: &mut W, descr: &str, shape: &[usize]) -> io::Result<()> {
+    let shape = match shape {
+        [n] => format!("({},)", n),
+        _ => {
+            let dims: Vec<String> = shape.iter().map(usize::to_string).collect();
+            format!("({})", dims.join(", "))
+        }
+    };
+    let mut header = format!(
+        "{{'descr': '{}', 'fortran_order': False, 'shape': {}, }}",
+        descr, shape
+    );
+    // the data start at a multiple of 64 bytes, after the magic string, the
+    // version, the length of the header and the header ending in a newline
+    let len = MAGIC.len() + 4 + header.len() + 1;
+    header.extend(iter::repeat(' ').take((64 - len % 64) % 64));
+    header.push('\n');
+
+    w.write_all(MAGIC)?;
+    w.write_all(&[1, 0])?;
+    w.write_all(&(header.len() as u16).to_le_bytes())?;
+    w.write_all(header.as_bytes())
+}
+
+// Reads an array in .npy format from `reader`. `name` identifies it in errors.
+pub(crate) fn parse<R: Read>(mut reader: R, name: &str) -> Result<ArrayD<f64>> {
+    let invalid = |message: &str| Error::InvalidFile {
+        path: name.to_string(),
+        message: message.to_string(),
+    };
+    let io = |err: std::io::Error| Error::Io(format!("{}: {}", name, err));
+
+    let mut preamble = [0u8; 8];
+    reader.read_exact(&mut preamble).map_err(io)?;
+    if &preamble[..6] != MAGIC {
+        return Err(invalid("not a .npy file"));
+    }
+    // version 1.0 stores the length of the header in 2 bytes, later ones in 4
+    let header_len = if preamble[6] == 1 {
+        let mut len = [0u8; 2];
+        reader.read_exact(&mut len).map_err(io)?;
+        u16::from_le_bytes(len) as usize
+    } else {
+        let mut len = [0u8; 4];
+        reader.read_exact(&mut len).map_err(io)?;
+        u32::from_le_bytes(len) as usize
+    };
+    let mut header = vec![0u8; header_len];
+    reader.read_exact(&mut header).map_err(io)?;
+    let header = String::from_utf8_lossy(&header);
+
+    let descr = value(&header, "descr")
+        .map(|descr| descr.trim_matches(|c| c == '\'' || c == '"'))
+        .ok_or_else(|| invalid("the header has no descr"))?;
+    let fortran_order = match value(&header, "fortran_order") {
+        Some("True") => true,
+        Some("False") => false,
+        _ => return Err(invalid("the header has no fortran_order")),
+    };
+    let shape = value(&header, "shape")
+        .and_then(parse_shape)
+        .ok_or_else(|| invalid("the header has no valid shape"))?;
+
+    let (size, little_endian) = match descr {
+        "<f8" => (8, true),
+        ">f8" => (8, false),
+        "<f4" => (4, true),
+        ">f4" => (4, false),
+        _ => {
+            return Err(invalid(&format!(
+                "holds values of type '{}', expected float64 or float32",
+                descr
+            )))
+        }
+    };
+
+    // the shape comes from the file, so its size may not fit in memory, or even
+    // in a usize
+    let len = shape
+        .iter()
+        .try_fold(size, |len, &dim| len.checked_mul(dim))
+        .ok_or_else(|| invalid(&format!("the shape {:?} is too large", shape)))?;
+    let mut bytes = Vec::new();
+    reader.read_to_end(&mut bytes).map_err(io)?;
+    if bytes.len() != len {
+        return Err(invalid(&format!(
+            "holds {} bytes of data, expected {} for shape {:?}",
+            bytes.len(),
+            len,
+            shape
+        )));
+    }
+    let data: Vec<f64> = bytes
+        .chunks(size)
+        .map(|chunk| match (size, little_endian) {
+            (8, true) => f64::from_le_bytes(chunk.try_into().unwrap()),
+            (8, false) => f64::from_be_bytes(chunk.try_into().unwrap()),
+            (_, true) => f64::from(f32::from_le_bytes(chunk.try_into().unwrap())),
+            (_, false) => f64::from(f32::from_be_bytes(chunk.try_into().unwrap())),
+        })
+        .collect();
+
+    let shape = IxDyn(&shape).set_f(fortran_order);
+    Ok(ArrayD::from_shape_vec(shape, data).unwrap())
+}
+
+// Returns the text of the value of `key` in the Python dict literal of a .npy
+// header, e.g. "(3, 4)" for 'shape'.
+fn value<'h>(header: &'h str, key: &str) -> Option<&'h str> {
+    let start = header.find(&format!("'{}':", key))? + key.len() + 3;
+    let rest = header[start..].trim_start();
+    let end = if rest.starts_with('(') {
+        rest.find(')')? + 1
+    } else {
+        rest.find(|c| c == ',' || c == '}')?
+    };
+    Some(rest[..end].trim())
+}
+
+// Parses a shape tuple, "(3, 4)", "(3,)" or "()".
+fn parse_shape(tuple: &str) -> Option<Vec<usize>> {
+    tuple
+        .trim_start_matches('(')
+        .trim_end_matches(')')
+        .split(',')
+        .map(str::trim)
+        .filter(|dim| !dim.is_empty())
+        .map(|dim| dim.parse().ok())
+        .collect()
+}
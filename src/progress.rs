@@ -0,0 +1,59 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Receives the progress of a calculation of B.
+///
+/// The calculation is split into `total` slabs: the planes of constant x of a
+/// target grid, the single points of [`field_at`], and for the FFT solver the
+/// four stages of transforming J, transforming the kernel, multiplying them
+/// and transforming back. `report` is called with `done` = 0 when a
+/// calculation starts, and again every time a slab is completed. The slabs are
+/// calculated in parallel, so `report` may be called from several threads at
+/// once.
+///
+/// Closures taking `done` and `total` implement the trait.
+///
+/// [`field_at`]: struct.BiotSavart.html#method.field_at
+pub trait Progress: Sync {
+    fn report(&self, done: usize, total: usize);
+}
+
+impl<F> Progress for F
+where
+    F: Fn(usize, usize) + Sync,
+{
+    fn report(&self, done: usize, total: usize) {
+        self(done, total)
+    }
+}
+
+// Counts the targets that are done and reports the completed slabs of `size`
+// targets each.
+pub(crate) struct Tally<'p> {
+    progress: Option<&'p dyn Progress>,
+    size: usize,
+    total: usize,
+    done: AtomicUsize,
+}
+
+impl<'p> Tally<'p> {
+    pub(crate) fn new(progress: Option<&'p dyn Progress>, size: usize, total: usize) -> Self {
+        if let Some(progress) = progress {
+            progress.report(0, total);
+        }
+        Tally {
+            progress,
+            size,
+            total,
+            done: AtomicUsize::new(0),
+        }
+    }
+
+    pub(crate) fn add(&self) {
+        if let Some(progress) = self.progress {
+            let done = self.done.fetch_add(1, Ordering::Relaxed) + 1;
+            if done % self.size == 0 {
+                progress.report(done / self.size, self.total);
+            }
+        }
+    }
+}
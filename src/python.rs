@@ -0,0 +1,2121 @@
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::panic;
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use crossbeam_utils::thread;
+
+use pyo3::class::{PyIterProtocol, PyMappingProtocol};
+use pyo3::create_exception;
+use pyo3::exceptions;
+use pyo3::ffi;
+use pyo3::prelude::*;
+use pyo3::types::{PyAny, PyDict};
+use pyo3::wrap_pyfunction;
+
+use log::{Level, LevelFilter, Log, Metadata, Record};
+
+use ndarray::{Array1, Array2, ArrayD, Axis, Dimension};
+use numpy::{IntoPyArray, PyArray, PyArray1, PyArray2, PyArray3, PyArray4};
+
+use crate::cancel::CancelToken;
+use crate::convention::Convention;
+use crate::cube::{Atom, Cube};
+use crate::current::HalfPlane;
+use crate::density::CurrentDensity;
+use crate::error::{Error, Result};
+use crate::field::VectorField;
+use crate::kernel::Backend;
+use crate::magnetizability::MagnetizabilityTensor;
+use crate::progress::Progress;
+use crate::shielding::{Shielding, ShieldingTensor};
+use crate::singularity::Singularity;
+use crate::solver::{BiotSavart, Solver};
+use crate::units::Units;
+
+// Base of the errors in the input of a calculation. It derives from ValueError,
+// so code catching ValueError keeps working.
+create_exception!(libbiot_savart, BiotSavartError, exceptions::ValueError);
+create_exception!(libbiot_savart, ShapeMismatchError, BiotSavartError);
+create_exception!(libbiot_savart, EmptyGridError, BiotSavartError);
+create_exception!(libbiot_savart, NonMonotonicError, BiotSavartError);
+create_exception!(libbiot_savart, NonFiniteError, BiotSavartError);
+create_exception!(libbiot_savart, NonUniformGridError, BiotSavartError);
+create_exception!(libbiot_savart, UnsupportedBackendError, BiotSavartError);
+create_exception!(libbiot_savart, InvalidFileError, BiotSavartError);
+// Raised when a calculation is stopped through its CancelToken.
+create_exception!(libbiot_savart, CancelledError, exceptions::RuntimeError);
+
+// Interval at which a running calculation checks for Ctrl-C
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+impl From<Error> for PyErr {
+    fn from(err: Error) -> PyErr {
+        let message = err.to_string();
+        match err {
+            Error::ShapeMismatch { .. } => ShapeMismatchError::py_err(message),
+            Error::EmptyGrid(_) => EmptyGridError::py_err(message),
+            Error::NonMonotonic(_) => NonMonotonicError::py_err(message),
+            Error::NonFinite(_) => NonFiniteError::py_err(message),
+            Error::NonUniform => NonUniformGridError::py_err(message),
+            Error::UnsupportedBackend(_) => UnsupportedBackendError::py_err(message),
+            Error::Cancelled => CancelledError::py_err(message),
+            Error::Io(_) => exceptions::OSError::py_err(message),
+            Error::InvalidFile { .. } => InvalidFileError::py_err(message),
+            Error::FftOffGrid | Error::InvalidPlane(_) | Error::InvalidTheta(_) => {
+                BiotSavartError::py_err(message)
+            }
+        }
+    }
+}
+
+/// Token to cancel running calculations from another thread
+///
+/// Pass it as the cancel argument of a calculation and call cancel() to stop
+/// it, which then raises CancelledError.
+#[pyclass(name = CancelToken)]
+struct PyCancelToken {
+    token: CancelToken,
+}
+
+#[pymethods]
+impl PyCancelToken {
+    #[new]
+    fn new(obj: &PyRawObject) {
+        obj.init(PyCancelToken {
+            token: CancelToken::new(),
+        });
+    }
+
+    /// Stops the calculations using the token
+    fn cancel(&self) -> PyResult<()> {
+        self.token.cancel();
+        Ok(())
+    }
+
+    /// True once cancel() has been called
+    #[getter]
+    fn cancelled(&self) -> PyResult<bool> {
+        Ok(self.token.is_cancelled())
+    }
+}
+
+// Returns the token a calculation checks. `run` cancels it on Ctrl-C or an
+// exception in the progress callable, which leaves the token of the caller, and
+// the other calculations sharing it, alone.
+fn cancel_token(cancel: Option<&PyCancelToken>) -> CancelToken {
+    cancel.map_or_else(CancelToken::new, |cancel| cancel.token.child())
+}
+
+// Forwards the log records of the crate to the loggers of the logging module,
+// with the module path as name, e.g. biot_savart.solver. The verbosity is set
+// on the Python side, and records below the level of their logger are dropped
+// before they are formatted.
+#[derive(Default)]
+struct PythonLogger {
+    // Loggers already looked up, by target
+    loggers: Mutex<HashMap<String, PyObject>>,
+}
+
+impl PythonLogger {
+    // Returns the logger of the target, looking it up on first use. The lock is
+    // not held while calling into Python, which may switch threads.
+    fn logger(&self, py: Python, target: &str) -> PyResult<PyObject> {
+        if let Some(logger) = self.loggers.lock().unwrap().get(target) {
+            return Ok(logger.clone_ref(py));
+        }
+        let logger = py
+            .import("logging")?
+            .call1("getLogger", (target.replace("::", "."),))?
+            .to_object(py);
+        self.loggers
+            .lock()
+            .unwrap()
+            .insert(target.to_string(), logger.clone_ref(py));
+        Ok(logger)
+    }
+
+    fn is_enabled_for(&self, py: Python, metadata: &Metadata) -> PyResult<bool> {
+        self.logger(py, metadata.target())?
+            .call_method1(py, "isEnabledFor", (python_level(metadata.level()),))?
+            .extract(py)
+    }
+}
+
+// Returns the level of the logging module matching `level`
+fn python_level(level: Level) -> i32 {
+    match level {
+        Level::Error => 40,
+        Level::Warn => 30,
+        Level::Info => 20,
+        Level::Debug => 10,
+        Level::Trace => 5,
+    }
+}
+
+impl Log for PythonLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        let gil = Python::acquire_gil();
+        let py = gil.python();
+        self.is_enabled_for(py, metadata).unwrap_or_else(|err| {
+            err.print(py);
+            false
+        })
+    }
+
+    fn log(&self, record: &Record) {
+        let gil = Python::acquire_gil();
+        let py = gil.python();
+        let result = self
+            .is_enabled_for(py, record.metadata())
+            .and_then(|enabled| {
+                if enabled {
+                    self.logger(py, record.target())?.call_method1(
+                        py,
+                        "log",
+                        (python_level(record.level()), record.args().to_string()),
+                    )?;
+                }
+                Ok(())
+            });
+        if let Err(err) = result {
+            err.print(py);
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+// Progress of a calculation, passed on to an optional Python callable. The
+// solver only records it, and `run` calls the callable from the thread holding
+// the GIL.
+struct PyProgress {
+    callback: Option<PyObject>,
+    state: Mutex<Option<(usize, usize)>>,
+}
+
+impl PyProgress {
+    fn new(callback: Option<PyObject>) -> Self {
+        PyProgress {
+            callback,
+            state: Mutex::new(None),
+        }
+    }
+
+    // Calls the callable if the progress changed since `last`.
+    fn update(&self, py: Python, last: &mut Option<(usize, usize)>) -> PyResult<()> {
+        let state = *self.state.lock().unwrap();
+        if let (Some(callback), Some(state)) = (&self.callback, state) {
+            if *last != Some(state) {
+                *last = Some(state);
+                callback.call1(py, state)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Progress for PyProgress {
+    fn report(&self, done: usize, total: usize) {
+        if self.callback.is_none() {
+            return;
+        }
+        let mut state = self.state.lock().unwrap();
+        // slabs are completed in parallel, so the reports can arrive out of order
+        *state = match *state {
+            Some((last, last_total)) if done > 0 && total == last_total => {
+                Some((last.max(done), total))
+            }
+            _ => Some((done, total)),
+        };
+    }
+}
+
+// Runs a calculation with the GIL released, so other Python threads keep
+// running meanwhile. The calculation runs on a separate thread, while this one
+// checks for signals, which Python only handles on the main thread, and calls
+// the progress callable. On Ctrl-C, or an exception in the callable, the
+// calculation is cancelled through `cancel`, which has to be the token of the
+// calculation from `cancel_token`, and the exception is raised.
+fn run<T, F>(py: Python, cancel: &CancelToken, progress: &PyProgress, f: F) -> PyResult<T>
+where
+    T: Send,
+    F: FnOnce() -> Result<T> + Send,
+{
+    let mut raised = false;
+    let mut last = None;
+    let result = py.allow_threads(|| {
+        let (done, finished) = mpsc::channel();
+        thread::scope(|s| {
+            let worker = s.spawn(move |_| {
+                let result = f();
+                // the receiver only goes away once the worker is joined
+                done.send(()).unwrap();
+                result
+            });
+            // a panic of the worker drops the sender, which also ends the wait
+            while let Err(RecvTimeoutError::Timeout) = finished.recv_timeout(POLL_INTERVAL) {
+                if !raised && poll(progress, &mut last) {
+                    raised = true;
+                    cancel.cancel();
+                }
+            }
+            worker.join()
+        })
+        .and_then(|result| result)
+    });
+
+    match result {
+        // KeyboardInterrupt or the exception of the callable is pending
+        Ok(_) if raised => Err(PyErr::fetch(py)),
+        Ok(result) => {
+            let result = result?;
+            progress.update(py, &mut last)?;
+            Ok(result)
+        }
+        Err(payload) => panic::resume_unwind(payload),
+    }
+}
+
+// Runs the Python signal handlers and the progress callable, and returns true
+// if one of them raised an exception, which is left pending.
+fn poll(progress: &PyProgress, last: &mut Option<(usize, usize)>) -> bool {
+    let gil = Python::acquire_gil();
+    let py = gil.python();
+    if unsafe { ffi::PyErr_CheckSignals() } != 0 {
+        return true;
+    }
+    match progress.update(py, last) {
+        Ok(()) => false,
+        Err(err) => {
+            err.restore(py);
+            true
+        }
+    }
+}
+
+// Borrows the data of a 1D array, which has to be contiguous.
+fn as_slice<'py>(name: &str, array: &'py PyArray1<f64>) -> PyResult<&'py [f64]> {
+    array
+        .as_slice()
+        .map_err(|_| exceptions::ValueError::py_err(format!("{} has to be contiguous", name)))
+}
+
+// Checks that a 3D array is either C or Fortran contiguous, so it can be
+// borrowed without copying.
+fn check_contiguous<D: Dimension>(name: &str, array: &PyArray<f64, D>) -> PyResult<()> {
+    if array.is_contiguous() {
+        Ok(())
+    } else {
+        Err(exceptions::ValueError::py_err(format!(
+            "{} has to be C or Fortran contiguous",
+            name
+        )))
+    }
+}
+
+// Parses the units and solver arguments shared by the Python functions.
+fn parse_options(units: &str, solver: &str, theta: f64) -> PyResult<(Units, Solver)> {
+    let units: Units = units.parse().map_err(exceptions::ValueError::py_err)?;
+    let solver = match solver.parse().map_err(exceptions::ValueError::py_err)? {
+        Solver::Tree(_) => Solver::Tree(theta),
+        solver => solver,
+    };
+    Ok((units, solver))
+}
+
+// Parses the singularity arguments shared by the Python functions.
+fn parse_singularity(singularity: &str, softening: f64) -> PyResult<Singularity> {
+    Singularity::parse(singularity, Some(softening)).map_err(exceptions::ValueError::py_err)
+}
+
+fn parse_backend(backend: &str) -> PyResult<Backend> {
+    backend.parse().map_err(exceptions::ValueError::py_err)
+}
+
+fn parse_convention(convention: &str) -> PyResult<Convention> {
+    convention.parse().map_err(exceptions::ValueError::py_err)
+}
+
+// Checks that a point, such as the center of the magnetization, has 3
+// coordinates.
+fn parse_point(name: &'static str, point: &PyArray1<f64>) -> PyResult<[f64; 3]> {
+    let point = as_slice(name, point)?;
+    if point.len() != 3 {
+        return Err(Error::ShapeMismatch {
+            name,
+            expected: vec![3],
+            found: vec![point.len()],
+        }
+        .into());
+    }
+    Ok([point[0], point[1], point[2]])
+}
+
+// Bx, By, Bz and the magnetization, as returned by biot
+type FieldAndMagnetization = (
+    Py<PyArray3<f64>>,
+    Py<PyArray3<f64>>,
+    Py<PyArray3<f64>>,
+    Py<PyArray1<f64>>,
+);
+
+// Calculates B on the grid of J and the magnetization around `center`.
+fn field_and_magnetization(
+    py: Python,
+    solver: &BiotSavart,
+    center: [f64; 3],
+    cancel: &CancelToken,
+    progress: &PyProgress,
+) -> PyResult<FieldAndMagnetization> {
+    let (m_vec, (b_x, b_y, b_z)) = run(py, cancel, progress, || {
+        let m_vec = solver.magnetization(center);
+        Ok((m_vec, solver.field()?))
+    })?;
+
+    Ok((
+        b_x.into_pyarray(py).to_owned(),
+        b_y.into_pyarray(py).to_owned(),
+        b_z.into_pyarray(py).to_owned(),
+        m_vec.to_vec().into_pyarray(py).to_owned(),
+    ))
+}
+
+/// Results of a calculation, with the sign convention of J they were calculated
+/// with
+///
+/// They index, iterate and unpack like values, the tuple or array of the
+/// results, e.g. bx, by, bz, m = biot(...).
+#[pyclass(name = Results)]
+struct PyResults {
+    values: PyObject,
+    convention: Convention,
+}
+
+#[pymethods]
+impl PyResults {
+    /// The tuple or array of the results
+    #[getter]
+    fn values(&self, py: Python) -> PyObject {
+        self.values.clone_ref(py)
+    }
+
+    /// Sign convention of J, 'current' or 'electron_flux'
+    #[getter]
+    fn convention(&self) -> String {
+        self.convention.to_string()
+    }
+}
+
+#[pyproto]
+impl PyMappingProtocol for PyResults {
+    fn __len__(&self) -> PyResult<usize> {
+        let gil = Python::acquire_gil();
+        let py = gil.python();
+        self.values.call_method0(py, "__len__")?.extract(py)
+    }
+
+    fn __getitem__(&self, key: &PyAny) -> PyResult<PyObject> {
+        let gil = Python::acquire_gil();
+        self.values
+            .call_method1(gil.python(), "__getitem__", (key,))
+    }
+}
+
+#[pyproto]
+impl PyIterProtocol for PyResults {
+    fn __iter__(slf: PyRefMut<Self>) -> PyResult<PyObject> {
+        let gil = Python::acquire_gil();
+        slf.values.call_method0(gil.python(), "__iter__")
+    }
+}
+
+// Returns `values`, a tuple or an array, as the Results of a calculation with J
+// in `convention`.
+fn results(py: Python, values: impl ToPyObject, convention: Convention) -> PyResult<PyObject> {
+    let results = PyResults {
+        values: values.to_object(py),
+        convention,
+    };
+    Ok(Py::new(py, results)?.to_object(py))
+}
+
+// Creates a solver borrowing the NumPy arrays of J and the grid.
+fn new_solver<'py>(
+    jx: &'py PyArray3<f64>,
+    jy: &'py PyArray3<f64>,
+    jz: &'py PyArray3<f64>,
+    x_cor: &'py PyArray1<f64>,
+    y_cor: &'py PyArray1<f64>,
+    z_cor: &'py PyArray1<f64>,
+) -> PyResult<BiotSavart<'py>> {
+    check_contiguous("jx", jx)?;
+    check_contiguous("jy", jy)?;
+    check_contiguous("jz", jz)?;
+
+    Ok(BiotSavart::new(
+        jx.as_array(),
+        jy.as_array(),
+        jz.as_array(),
+        as_slice("x_cor", x_cor)?,
+        as_slice("y_cor", y_cor)?,
+        as_slice("z_cor", z_cor)?,
+    )?)
+}
+
+/// Calculates the magnetic field, B, generated by a current density, J
+///
+/// Parameters
+/// ----------
+/// center : ndarray
+///     Array of x-, y-, z- coordinates where the magnetization is calculated
+/// jx : ndarray
+///     Values of Jx on a 3D grid. Has to be a float64 array of size MxNxK.
+/// jy : ndarray
+///     Values of Jy on a 3D grid. Has to be a float64 array of size MxNxK.
+/// jz : ndarray
+///     Values of Jz on a 3D grid. Has to be a float64 array of size MxNxK.
+/// x_cor : ndarray
+///     X coordinates for the first dimension of the J values grid.
+/// y_cor : ndarray
+///     Y coordinates for the second dimension of the J values grid.
+/// z_cor : ndarray
+///     Z coordinates for the third dimension of the J values grid.
+/// units : str, optional
+///     Unit system of the input and the results, one of 'au' (default), 'si'
+///     or 'gaussian'.
+/// solver : str, optional
+///     'direct' (default) sums over all grid points, 'fft' calculates B as a
+///     convolution, which is much faster but requires a uniformly spaced grid,
+///     and 'tree' uses a Barnes-Hut tree code, which works for any grid.
+/// theta : float, optional
+///     Opening angle of the tree code, 0.5 by default. Smaller is more accurate.
+/// singularity : str, optional
+///     Treatment of targets at grid points of J. 'exclude' (default) leaves the
+///     grid point out, 'analytic' integrates its voxel with J to first order,
+///     and 'soften' uses the kernel r / (r² + softening²)^(3/2).
+/// softening : float, optional
+///     Softening length used with singularity='soften', which can also be
+///     given as singularity='soften:<length>'.
+/// backend : str, optional
+///     Instruction set of the direct sum, one of 'auto' (default), 'scalar',
+///     'sse2', 'sse41' or 'avx2'. Forcing one makes results reproducible
+///     across CPUs.
+/// cancel : CancelToken, optional
+///     Token to stop the calculation from another thread.
+/// progress : callable, optional
+///     Called as progress(done, total) while the calculation runs, with the
+///     number of completed slabs of targets and the total number of slabs.
+/// convention : str, optional
+///     Sign convention of J, 'current' (default) for the electric current
+///     density, or 'electron_flux' for the flux of the electrons, its negative.
+///
+/// The J arrays have to be C or Fortran contiguous, and are used without copying.
+///
+/// Raises
+/// ------
+/// ShapeMismatchError
+///     If the J arrays do not match the coordinates, or center is not of length 3.
+/// EmptyGridError
+///     If a coordinate array is empty.
+/// NonMonotonicError
+///     If coordinates are not strictly increasing or decreasing.
+/// NonFiniteError
+///     If the input contains NaN or infinite values.
+/// NonUniformGridError
+///     If solver='fft' is used with a grid that is not uniformly spaced.
+/// UnsupportedBackendError
+///     If the CPU does not support the forced backend.
+///
+/// All of these derive from BiotSavartError, which derives from ValueError.
+///
+/// The calculation runs without holding the GIL. Ctrl-C stops it and raises
+/// KeyboardInterrupt, and cancelling the token raises CancelledError.
+///
+/// Returns
+/// -------
+/// B : Results
+///     tuple of Bx, By and Bz, each of size MxNxK, and the magnetization as an
+///     array of length 3, with the sign convention of J as its convention
+///     attribute.
+#[pyfunction(
+    units = "\"au\"",
+    solver = "\"direct\"",
+    theta = "0.5",
+    singularity = "\"exclude\"",
+    softening = "0.0",
+    backend = "\"auto\"",
+    cancel = "None",
+    progress = "None",
+    convention = "\"current\""
+)]
+fn biot(
+    py: Python,
+    center: &PyArray1<f64>,
+    jx: &PyArray3<f64>,
+    jy: &PyArray3<f64>,
+    jz: &PyArray3<f64>,
+    x_cor: &PyArray1<f64>,
+    y_cor: &PyArray1<f64>,
+    z_cor: &PyArray1<f64>,
+    units: &str,
+    solver: &str,
+    theta: f64,
+    singularity: &str,
+    softening: f64,
+    backend: &str,
+    cancel: Option<&PyCancelToken>,
+    progress: Option<PyObject>,
+    convention: &str,
+) -> PyResult<PyObject> {
+    let center = parse_point("center", center)?;
+    let (units, method) = parse_options(units, solver, theta)?;
+    let singularity = parse_singularity(singularity, softening)?;
+    let convention = parse_convention(convention)?;
+
+    let cancel = cancel_token(cancel);
+    let progress = PyProgress::new(progress);
+
+    let solver = new_solver(jx, jy, jz, x_cor, y_cor, z_cor)?
+        .units(units)
+        .convention(convention)
+        .solver(method)
+        .singularity(singularity)
+        .backend(parse_backend(backend)?)
+        .cancel_token(cancel.clone())
+        .progress(&progress);
+
+    let result = field_and_magnetization(py, &solver, center, &cancel, &progress)?;
+    results(py, result, convention)
+}
+
+/// Calculates the magnetic field, B, generated by a current density, J, read
+/// from Gaussian cube files
+///
+/// Parameters
+/// ----------
+/// center : ndarray
+///     Array of x-, y-, z- coordinates where the magnetization is calculated
+/// jx : str
+///     Path of the cube file of Jx.
+/// jy : str
+///     Path of the cube file of Jy.
+/// jz : str
+///     Path of the cube file of Jz.
+/// solver : str, optional
+///     'direct' (default), 'fft' or 'tree', as for biot.
+/// theta : float, optional
+///     Opening angle of the tree code, 0.5 by default. Smaller is more accurate.
+/// singularity : str, optional
+///     'exclude' (default), 'analytic' or 'soften', as for biot.
+/// softening : float, optional
+///     Softening length used with singularity='soften', which can also be
+///     given as singularity='soften:<length>'.
+/// backend : str, optional
+///     Instruction set of the direct sum, one of 'auto' (default), 'scalar',
+///     'sse2', 'sse41' or 'avx2'.
+/// cancel : CancelToken, optional
+///     Token to stop the calculation from another thread.
+/// progress : callable, optional
+///     Called as progress(done, total) while the calculation runs, with the
+///     number of completed slabs of targets and the total number of slabs.
+/// convention : str, optional
+///     'current' (default) or 'electron_flux', as for biot.
+///
+/// The files have to share a grid with voxel vectors along x, y and z. The
+/// input and the results are in atomic units.
+///
+/// Raises
+/// ------
+/// OSError
+///     If a file cannot be read.
+/// InvalidFileError
+///     If a file is not a cube file, or the files do not share a grid.
+///
+/// Returns
+/// -------
+/// B : Results
+///     tuple of Bx, By and Bz, each with the shape of the cube grid, and the
+///     magnetization as an array of length 3, with the sign convention of J as
+///     its convention attribute.
+#[pyfunction(
+    solver = "\"direct\"",
+    theta = "0.5",
+    singularity = "\"exclude\"",
+    softening = "0.0",
+    backend = "\"auto\"",
+    cancel = "None",
+    progress = "None",
+    convention = "\"current\""
+)]
+fn biot_cube(
+    py: Python,
+    center: &PyArray1<f64>,
+    jx: &str,
+    jy: &str,
+    jz: &str,
+    solver: &str,
+    theta: f64,
+    singularity: &str,
+    softening: f64,
+    backend: &str,
+    cancel: Option<&PyCancelToken>,
+    progress: Option<PyObject>,
+    convention: &str,
+) -> PyResult<PyObject> {
+    let center = parse_point("center", center)?;
+    let (_, method) = parse_options("au", solver, theta)?;
+    let singularity = parse_singularity(singularity, softening)?;
+    let convention = parse_convention(convention)?;
+
+    let density = CurrentDensity::from_cubes(jx, jy, jz)?;
+    let cancel = cancel_token(cancel);
+    let progress = PyProgress::new(progress);
+
+    let solver = density
+        .solver()?
+        .convention(convention)
+        .solver(method)
+        .singularity(singularity)
+        .backend(parse_backend(backend)?)
+        .cancel_token(cancel.clone())
+        .progress(&progress);
+
+    let result = field_and_magnetization(py, &solver, center, &cancel, &progress)?;
+    results(py, result, convention)
+}
+
+/// Reads a Gaussian cube file
+///
+/// Parameters
+/// ----------
+/// path : str
+///     Path of the cube file.
+///
+/// Lengths are returned in bohr, files in Ångström are converted.
+///
+/// Returns
+/// -------
+/// cube : dict
+///     'comments', the two comment lines, 'origin', 'axes', a 3x3 array with
+///     the voxel vectors as rows, 'numbers', 'charges' and 'positions' of the
+///     atoms, and 'data', the values on the grid.
+#[pyfunction]
+fn read_cube(py: Python, path: &str) -> PyResult<PyObject> {
+    let cube = Cube::read(path)?;
+    let numbers: Array1<i32> = cube.atoms.iter().map(|atom| atom.number).collect();
+    let charges: Array1<f64> = cube.atoms.iter().map(|atom| atom.charge).collect();
+    let positions =
+        Array2::from_shape_fn((cube.atoms.len(), 3), |(n, a)| cube.atoms[n].position[a]);
+    let axes = Array2::from_shape_fn((3, 3), |(n, a)| cube.axes[n][a]);
+
+    let result = PyDict::new(py);
+    result.set_item("comments", cube.comments.to_vec())?;
+    result.set_item("origin", cube.origin.to_vec().into_pyarray(py))?;
+    result.set_item("axes", axes.into_pyarray(py))?;
+    result.set_item("numbers", numbers.into_pyarray(py))?;
+    result.set_item("charges", charges.into_pyarray(py))?;
+    result.set_item("positions", positions.into_pyarray(py))?;
+    result.set_item("data", cube.data.into_pyarray(py))?;
+    Ok(result.to_object(py))
+}
+
+/// Reads the current density written by GIMIC
+///
+/// Parameters
+/// ----------
+/// path : str
+///     Path of jvec.txt, with lines of 'x y z jx jy jz', or of jvec.vti, VTK
+///     ImageData in ASCII format.
+///
+/// GIMIC writes lengths in bohr and J in atomic units, so the arrays can be
+/// passed to biot with units='au'. The grid has to be along x, y and z.
+///
+/// Returns
+/// -------
+/// dict
+///     'jx', 'jy' and 'jz' on the grid, and its coordinates 'x_cor', 'y_cor'
+///     and 'z_cor'.
+#[pyfunction]
+fn read_gimic(py: Python, path: &str) -> PyResult<PyObject> {
+    let density = CurrentDensity::from_gimic(path)?;
+    let result = PyDict::new(py);
+    result.set_item("jx", density.jx.into_pyarray(py))?;
+    result.set_item("jy", density.jy.into_pyarray(py))?;
+    result.set_item("jz", density.jz.into_pyarray(py))?;
+    result.set_item("x_cor", density.x_cor.into_pyarray(py))?;
+    result.set_item("y_cor", density.y_cor.into_pyarray(py))?;
+    result.set_item("z_cor", density.z_cor.into_pyarray(py))?;
+    Ok(result.to_object(py))
+}
+
+// Collects the atoms given as atomic numbers and an Nx3 array of positions.
+// The numbers can be a sequence of any integer type, as NumPy defaults to
+// int64 on most platforms but int32 on Windows.
+fn atoms(numbers: Option<&PyAny>, positions: Option<&PyArray2<f64>>) -> PyResult<Vec<Atom>> {
+    let (numbers, positions) = match (numbers, positions) {
+        (Some(numbers), Some(positions)) => (numbers.extract::<Vec<i64>>()?, positions.as_array()),
+        (None, None) => return Ok(Vec::new()),
+        _ => {
+            return Err(exceptions::ValueError::py_err(
+                "numbers and positions have to be given together",
+            ))
+        }
+    };
+    if positions.shape() != [numbers.len(), 3] {
+        return Err(Error::ShapeMismatch {
+            name: "positions",
+            expected: vec![numbers.len(), 3],
+            found: positions.shape().to_vec(),
+        }
+        .into());
+    }
+
+    numbers
+        .iter()
+        .zip(positions.genrows())
+        .map(|(&number, r)| {
+            let number = i32::try_from(number).map_err(|_| {
+                exceptions::ValueError::py_err(format!("invalid atomic number {}", number))
+            })?;
+            Ok(Atom {
+                number,
+                charge: f64::from(number),
+                position: [r[0], r[1], r[2]],
+            })
+        })
+        .collect()
+}
+
+/// Writes a vector field to Gaussian cube files
+///
+/// Parameters
+/// ----------
+/// stem : str
+///     Start of the file names. The components are written to stem_x.cube,
+///     stem_y.cube and stem_z.cube, and the length of the vectors to
+///     stem_norm.cube.
+/// bx : ndarray
+///     X components of the field, of size MxNxK.
+/// by : ndarray
+///     Y components of the field, of size MxNxK.
+/// bz : ndarray
+///     Z components of the field, of size MxNxK.
+/// x_cor : ndarray
+///     X coordinates for the first dimension of the grid.
+/// y_cor : ndarray
+///     Y coordinates for the second dimension of the grid.
+/// z_cor : ndarray
+///     Z coordinates for the third dimension of the grid.
+/// name : str, optional
+///     Name of the field in the comments of the files, 'B' by default.
+/// numbers : array_like, optional
+///     Atomic numbers of the atoms listed in the files, of any integer type.
+/// positions : ndarray, optional
+///     Nx3 array of the positions of the atoms.
+///
+/// The coordinates have to be uniformly spaced and in bohr.
+///
+/// Returns
+/// -------
+/// paths : list of str
+///     Paths of the files written.
+#[pyfunction(name = "\"B\"", numbers = "None", positions = "None")]
+fn write_cubes(
+    stem: &str,
+    bx: &PyArray3<f64>,
+    by: &PyArray3<f64>,
+    bz: &PyArray3<f64>,
+    x_cor: &PyArray1<f64>,
+    y_cor: &PyArray1<f64>,
+    z_cor: &PyArray1<f64>,
+    name: &str,
+    numbers: Option<&PyAny>,
+    positions: Option<&PyArray2<f64>>,
+) -> PyResult<Vec<String>> {
+    let field = VectorField::new(
+        bx.as_array(),
+        by.as_array(),
+        bz.as_array(),
+        as_slice("x_cor", x_cor)?,
+        as_slice("y_cor", y_cor)?,
+        as_slice("z_cor", z_cor)?,
+    )?;
+    let paths = field.write_cubes(stem, name, &atoms(numbers, positions)?)?;
+    Ok(paths
+        .iter()
+        .map(|path| path.display().to_string())
+        .collect())
+}
+
+/// Writes a vector field to a VTK XML file
+///
+/// Parameters
+/// ----------
+/// path : str
+///     Path of the file. Files ending in .vti are written as ImageData, which
+///     requires uniformly spaced coordinates, and files ending in .vtr as
+///     RectilinearGrid.
+/// bx : ndarray
+///     X components of the field, of size MxNxK.
+/// by : ndarray
+///     Y components of the field, of size MxNxK.
+/// bz : ndarray
+///     Z components of the field, of size MxNxK.
+/// x_cor : ndarray
+///     X coordinates for the first dimension of the grid.
+/// y_cor : ndarray
+///     Y coordinates for the second dimension of the grid.
+/// z_cor : ndarray
+///     Z coordinates for the third dimension of the grid.
+/// name : str, optional
+///     Name of the vector point data, 'B' by default. The length of the vectors
+///     is written as name_norm.
+#[pyfunction(name = "\"B\"")]
+fn write_vtk(
+    path: &str,
+    bx: &PyArray3<f64>,
+    by: &PyArray3<f64>,
+    bz: &PyArray3<f64>,
+    x_cor: &PyArray1<f64>,
+    y_cor: &PyArray1<f64>,
+    z_cor: &PyArray1<f64>,
+    name: &str,
+) -> PyResult<()> {
+    let field = VectorField::new(
+        bx.as_array(),
+        by.as_array(),
+        bz.as_array(),
+        as_slice("x_cor", x_cor)?,
+        as_slice("y_cor", y_cor)?,
+        as_slice("z_cor", z_cor)?,
+    )?;
+    if path.ends_with(".vti") {
+        Ok(field.write_vti(path, name)?)
+    } else if path.ends_with(".vtr") {
+        Ok(field.write_vtr(path, name)?)
+    } else {
+        Err(exceptions::ValueError::py_err(format!(
+            "{} does not end in .vti or .vtr",
+            path
+        )))
+    }
+}
+
+/// Calculates the magnetic field, B, generated by a current density, J, at
+/// arbitrary points
+///
+/// Parameters
+/// ----------
+/// points : ndarray
+///     Nx3 array of x-, y-, z- coordinates where B is calculated.
+/// jx : ndarray
+///     Values of Jx on a 3D grid. Has to be a float64 array of size MxNxK.
+/// jy : ndarray
+///     Values of Jy on a 3D grid. Has to be a float64 array of size MxNxK.
+/// jz : ndarray
+///     Values of Jz on a 3D grid. Has to be a float64 array of size MxNxK.
+/// x_cor : ndarray
+///     X coordinates for the first dimension of the J values grid.
+/// y_cor : ndarray
+///     Y coordinates for the second dimension of the J values grid.
+/// z_cor : ndarray
+///     Z coordinates for the third dimension of the J values grid.
+/// units : str, optional
+///     Unit system of the input and the results, one of 'au' (default), 'si'
+///     or 'gaussian'.
+/// solver : str, optional
+///     'direct' (default) or 'tree'.
+/// theta : float, optional
+///     Opening angle of the tree code, 0.5 by default. Smaller is more accurate.
+/// singularity : str, optional
+///     Treatment of targets at grid points of J. 'exclude' (default) leaves the
+///     grid point out, 'analytic' integrates its voxel with J to first order,
+///     and 'soften' uses the kernel r / (r² + softening²)^(3/2).
+/// softening : float, optional
+///     Softening length used with singularity='soften', which can also be
+///     given as singularity='soften:<length>'.
+/// backend : str, optional
+///     Instruction set of the direct sum, one of 'auto' (default), 'scalar',
+///     'sse2', 'sse41' or 'avx2'. Forcing one makes results reproducible
+///     across CPUs.
+/// cancel : CancelToken, optional
+///     Token to stop the calculation from another thread.
+/// progress : callable, optional
+///     Called as progress(done, total) while the calculation runs, with the
+///     number of completed slabs of targets and the total number of slabs.
+/// convention : str, optional
+///     Sign convention of J, 'current' (default) for the electric current
+///     density, or 'electron_flux' for the flux of the electrons, its negative.
+///
+/// Returns
+/// -------
+/// B : Results
+///     Nx3 array of Bx, By and Bz at each point, with the sign convention of J
+///     as its convention attribute. The array itself is its values attribute.
+#[pyfunction(
+    units = "\"au\"",
+    solver = "\"direct\"",
+    theta = "0.5",
+    singularity = "\"exclude\"",
+    softening = "0.0",
+    backend = "\"auto\"",
+    cancel = "None",
+    progress = "None",
+    convention = "\"current\""
+)]
+fn biot_points(
+    py: Python,
+    points: &PyArray2<f64>,
+    jx: &PyArray3<f64>,
+    jy: &PyArray3<f64>,
+    jz: &PyArray3<f64>,
+    x_cor: &PyArray1<f64>,
+    y_cor: &PyArray1<f64>,
+    z_cor: &PyArray1<f64>,
+    units: &str,
+    solver: &str,
+    theta: f64,
+    singularity: &str,
+    softening: f64,
+    backend: &str,
+    cancel: Option<&PyCancelToken>,
+    progress: Option<PyObject>,
+    convention: &str,
+) -> PyResult<PyObject> {
+    let (units, method) = parse_options(units, solver, theta)?;
+    let singularity = parse_singularity(singularity, softening)?;
+    let convention = parse_convention(convention)?;
+
+    let cancel = cancel_token(cancel);
+    let progress = PyProgress::new(progress);
+
+    let solver = new_solver(jx, jy, jz, x_cor, y_cor, z_cor)?
+        .units(units)
+        .convention(convention)
+        .solver(method)
+        .singularity(singularity)
+        .backend(parse_backend(backend)?)
+        .cancel_token(cancel.clone())
+        .progress(&progress);
+
+    let points = points.as_array();
+    let b = run(py, &cancel, &progress, || solver.field_at(points))?;
+    results(py, b.into_pyarray(py), convention)
+}
+
+// Creates the solvers of the current densities induced by external fields along
+// x, y and z, which are stacked along the first dimension of the J arrays.
+// `configure` sets the options of each solver.
+fn response_solvers<'a, F>(
+    jx: &'a PyArray4<f64>,
+    jy: &'a PyArray4<f64>,
+    jz: &'a PyArray4<f64>,
+    x_cor: &'a PyArray1<f64>,
+    y_cor: &'a PyArray1<f64>,
+    z_cor: &'a PyArray1<f64>,
+    configure: F,
+) -> PyResult<[BiotSavart<'a>; 3]>
+where
+    F: Fn(BiotSavart<'a>) -> BiotSavart<'a>,
+{
+    for &(name, j) in &[("jx", jx), ("jy", jy), ("jz", jz)] {
+        check_contiguous(name, j)?;
+        if j.shape()[0] != 3 {
+            let mut expected = j.shape().to_vec();
+            expected[0] = 3;
+            return Err(Error::ShapeMismatch {
+                name,
+                expected,
+                found: j.shape().to_vec(),
+            }
+            .into());
+        }
+    }
+
+    let (x_cor, y_cor, z_cor) = (
+        as_slice("x_cor", x_cor)?,
+        as_slice("y_cor", y_cor)?,
+        as_slice("z_cor", z_cor)?,
+    );
+    let response = |b: usize| -> PyResult<BiotSavart<'a>> {
+        let solver = BiotSavart::new(
+            jx.as_array().index_axis_move(Axis(0), b),
+            jy.as_array().index_axis_move(Axis(0), b),
+            jz.as_array().index_axis_move(Axis(0), b),
+            x_cor,
+            y_cor,
+            z_cor,
+        )?;
+        Ok(configure(solver))
+    };
+    Ok([response(0)?, response(1)?, response(2)?])
+}
+
+/// Calculates the magnetic shielding tensor at arbitrary points, for NICS
+///
+/// Parameters
+/// ----------
+/// points : ndarray
+///     Nx3 array of x-, y-, z- coordinates of the probe points.
+/// jx : ndarray
+///     Values of Jx induced by unit external fields along x, y and z. Has to be
+///     a float64 array of size 3xMxNxK, where jx[b] is the response to the
+///     field along b.
+/// jy : ndarray
+///     Values of Jy, as jx.
+/// jz : ndarray
+///     Values of Jz, as jx.
+/// x_cor : ndarray
+///     X coordinates for the first dimension of the J values grid.
+/// y_cor : ndarray
+///     Y coordinates for the second dimension of the J values grid.
+/// z_cor : ndarray
+///     Z coordinates for the third dimension of the J values grid.
+/// units : str, optional
+///     Unit system of the input, one of 'au' (default), 'si' or 'gaussian'.
+/// solver : str, optional
+///     'direct' (default) or 'tree'.
+/// theta : float, optional
+///     Opening angle of the tree code, 0.5 by default. Smaller is more accurate.
+/// singularity : str, optional
+///     'exclude' (default), 'analytic' or 'soften', as for biot.
+/// softening : float, optional
+///     Softening length used with singularity='soften', which can also be
+///     given as singularity='soften:<length>'.
+/// backend : str, optional
+///     Instruction set of the direct sum, one of 'auto' (default), 'scalar',
+///     'sse2', 'sse41' or 'avx2'.
+/// cancel : CancelToken, optional
+///     Token to stop the calculation from another thread.
+/// progress : callable, optional
+///     Called as progress(done, total) while the calculation runs, once for
+///     each of the three field directions.
+/// convention : str, optional
+///     Sign convention of J, 'current' (default) for the electric current
+///     density, or 'electron_flux' for the flux of the electrons, its negative.
+///
+/// J has to be the response to an external field of 1 in the chosen units, as
+/// the current densities of GIMIC are in atomic units. The shielding does not
+/// depend on the units then.
+///
+/// Returns
+/// -------
+/// dict
+///     'tensor', an Nx3x3 array of the shielding in ppm, where tensor[n, a, b]
+///     is minus the induced field along a per unit external field along b, and
+///     arrays of length N of its 'isotropic' value, 'anisotropy', the
+///     σ33 - (σ11 + σ22) / 2 of the principal components of its symmetric part,
+///     and 'zz' component. NICS is -isotropic, and NICS_zz is -zz. The sign
+///     'convention' of J is recorded as well.
+#[pyfunction(
+    units = "\"au\"",
+    solver = "\"direct\"",
+    theta = "0.5",
+    singularity = "\"exclude\"",
+    softening = "0.0",
+    backend = "\"auto\"",
+    cancel = "None",
+    progress = "None",
+    convention = "\"current\""
+)]
+fn shielding(
+    py: Python,
+    points: &PyArray2<f64>,
+    jx: &PyArray4<f64>,
+    jy: &PyArray4<f64>,
+    jz: &PyArray4<f64>,
+    x_cor: &PyArray1<f64>,
+    y_cor: &PyArray1<f64>,
+    z_cor: &PyArray1<f64>,
+    units: &str,
+    solver: &str,
+    theta: f64,
+    singularity: &str,
+    softening: f64,
+    backend: &str,
+    cancel: Option<&PyCancelToken>,
+    progress: Option<PyObject>,
+    convention: &str,
+) -> PyResult<PyObject> {
+    let (units, method) = parse_options(units, solver, theta)?;
+    let singularity = parse_singularity(singularity, softening)?;
+    let convention = parse_convention(convention)?;
+    let backend = parse_backend(backend)?;
+
+    let cancel = cancel_token(cancel);
+    let progress = PyProgress::new(progress);
+
+    let [x, y, z] = response_solvers(jx, jy, jz, x_cor, y_cor, z_cor, |solver| {
+        solver
+            .units(units)
+            .convention(convention)
+            .solver(method)
+            .singularity(singularity)
+            .backend(backend)
+            .cancel_token(cancel.clone())
+            .progress(&progress)
+    })?;
+    let shielding = Shielding::new(x, y, z);
+
+    let points = points.as_array();
+    let tensors = run(py, &cancel, &progress, || shielding.at(points))?;
+    Ok(shielding_dict(py, &tensors, &[tensors.len()], convention)?.to_object(py))
+}
+
+/// Calculates the magnetic shielding tensor along a line, for NICS scans
+///
+/// Parameters
+/// ----------
+/// start : ndarray
+///     Coordinates of the first point of the line.
+/// end : ndarray
+///     Coordinates of the last point of the line.
+/// n : int
+///     Number of evenly spaced points from start to end.
+/// jx, jy, jz, x_cor, y_cor, z_cor
+///     Current densities induced by unit external fields along x, y and z, and
+///     their grid, as for shielding.
+/// units, solver, theta, singularity, softening, backend, cancel, progress,
+/// convention
+///     Options, as for shielding.
+///
+/// For NICS(0) and NICS(1) of a ring, the line runs along the normal through
+/// the center of the ring, where distance 0 gives NICS(0) and a distance of
+/// 1 Å, 1.89 bohr, gives NICS(1).
+///
+/// Returns
+/// -------
+/// dict
+///     'distances' of the points from start, their Nx3 'points', and the
+///     'tensor', 'isotropic', 'anisotropy' and 'zz' arrays of shielding, and
+///     the sign 'convention' of J.
+#[pyfunction(
+    units = "\"au\"",
+    solver = "\"direct\"",
+    theta = "0.5",
+    singularity = "\"exclude\"",
+    softening = "0.0",
+    backend = "\"auto\"",
+    cancel = "None",
+    progress = "None",
+    convention = "\"current\""
+)]
+fn shielding_line(
+    py: Python,
+    start: &PyArray1<f64>,
+    end: &PyArray1<f64>,
+    n: usize,
+    jx: &PyArray4<f64>,
+    jy: &PyArray4<f64>,
+    jz: &PyArray4<f64>,
+    x_cor: &PyArray1<f64>,
+    y_cor: &PyArray1<f64>,
+    z_cor: &PyArray1<f64>,
+    units: &str,
+    solver: &str,
+    theta: f64,
+    singularity: &str,
+    softening: f64,
+    backend: &str,
+    cancel: Option<&PyCancelToken>,
+    progress: Option<PyObject>,
+    convention: &str,
+) -> PyResult<PyObject> {
+    let start = parse_point("start", start)?;
+    let end = parse_point("end", end)?;
+    let (units, method) = parse_options(units, solver, theta)?;
+    let singularity = parse_singularity(singularity, softening)?;
+    let convention = parse_convention(convention)?;
+    let backend = parse_backend(backend)?;
+
+    let cancel = cancel_token(cancel);
+    let progress = PyProgress::new(progress);
+
+    let [x, y, z] = response_solvers(jx, jy, jz, x_cor, y_cor, z_cor, |solver| {
+        solver
+            .units(units)
+            .convention(convention)
+            .solver(method)
+            .singularity(singularity)
+            .backend(backend)
+            .cancel_token(cancel.clone())
+            .progress(&progress)
+    })?;
+    let shielding = Shielding::new(x, y, z);
+
+    let scan = run(py, &cancel, &progress, || shielding.line(start, end, n))?;
+    let result = shielding_dict(py, &scan.tensors, &[n], convention)?;
+    result.set_item("distances", scan.distances.into_pyarray(py))?;
+    result.set_item("points", scan.points.into_pyarray(py))?;
+    Ok(result.to_object(py))
+}
+
+/// Calculates the magnetic shielding tensor over a plane, for NICS maps
+///
+/// Parameters
+/// ----------
+/// corner : ndarray
+///     Coordinates of a corner of the plane.
+/// u : ndarray
+///     First edge of the plane, from the corner.
+/// v : ndarray
+///     Second edge of the plane, from the corner.
+/// nu : int
+///     Number of evenly spaced points along u.
+/// nv : int
+///     Number of evenly spaced points along v.
+/// jx, jy, jz, x_cor, y_cor, z_cor
+///     Current densities induced by unit external fields along x, y and z, and
+///     their grid, as for shielding.
+/// units, solver, theta, singularity, softening, backend, cancel, progress,
+/// convention
+///     Options, as for shielding.
+///
+/// The points, corner + i / (nu - 1) * u + j / (nv - 1) * v, span the
+/// parallelogram with edges u and v. A map centered above a molecule in the xy
+/// plane at height h has the corner (-a, -a, h) and the edges (2a, 0, 0) and
+/// (0, 2a, 0).
+///
+/// Returns
+/// -------
+/// dict
+///     'u' and 'v', the distances of the points from the corner along the
+///     edges, the NuxNvx3 'points', and the 'tensor', 'isotropic', 'anisotropy'
+///     and 'zz' arrays of shielding, of size NuxNvx3x3 and NuxNv, and the sign
+///     'convention' of J.
+#[pyfunction(
+    units = "\"au\"",
+    solver = "\"direct\"",
+    theta = "0.5",
+    singularity = "\"exclude\"",
+    softening = "0.0",
+    backend = "\"auto\"",
+    cancel = "None",
+    progress = "None",
+    convention = "\"current\""
+)]
+fn shielding_plane(
+    py: Python,
+    corner: &PyArray1<f64>,
+    u: &PyArray1<f64>,
+    v: &PyArray1<f64>,
+    nu: usize,
+    nv: usize,
+    jx: &PyArray4<f64>,
+    jy: &PyArray4<f64>,
+    jz: &PyArray4<f64>,
+    x_cor: &PyArray1<f64>,
+    y_cor: &PyArray1<f64>,
+    z_cor: &PyArray1<f64>,
+    units: &str,
+    solver: &str,
+    theta: f64,
+    singularity: &str,
+    softening: f64,
+    backend: &str,
+    cancel: Option<&PyCancelToken>,
+    progress: Option<PyObject>,
+    convention: &str,
+) -> PyResult<PyObject> {
+    let corner = parse_point("corner", corner)?;
+    let u = parse_point("u", u)?;
+    let v = parse_point("v", v)?;
+    let (units, method) = parse_options(units, solver, theta)?;
+    let singularity = parse_singularity(singularity, softening)?;
+    let convention = parse_convention(convention)?;
+    let backend = parse_backend(backend)?;
+
+    let cancel = cancel_token(cancel);
+    let progress = PyProgress::new(progress);
+
+    let [x, y, z] = response_solvers(jx, jy, jz, x_cor, y_cor, z_cor, |solver| {
+        solver
+            .units(units)
+            .convention(convention)
+            .solver(method)
+            .singularity(singularity)
+            .backend(backend)
+            .cancel_token(cancel.clone())
+            .progress(&progress)
+    })?;
+    let shielding = Shielding::new(x, y, z);
+
+    let plane = run(py, &cancel, &progress, || {
+        shielding.plane(corner, u, v, nu, nv)
+    })?;
+    let tensors: Vec<ShieldingTensor> = plane.tensors.iter().cloned().collect();
+    let result = shielding_dict(py, &tensors, &[nu, nv], convention)?;
+    result.set_item("u", plane.u.into_pyarray(py))?;
+    result.set_item("v", plane.v.into_pyarray(py))?;
+    result.set_item("points", plane.points.into_pyarray(py))?;
+    Ok(result.to_object(py))
+}
+
+// Collects shielding tensors at points of `shape` into the dict returned by
+// the shielding functions.
+fn shielding_dict<'py>(
+    py: Python<'py>,
+    tensors: &[ShieldingTensor],
+    shape: &[usize],
+    convention: Convention,
+) -> PyResult<&'py PyDict> {
+    let values = |value: fn(&ShieldingTensor) -> f64| {
+        ArrayD::from_shape_vec(shape, tensors.iter().map(value).collect()).unwrap()
+    };
+    let tensor_shape: Vec<usize> = shape.iter().chain(&[3, 3]).cloned().collect();
+    let tensor: Vec<f64> = tensors
+        .iter()
+        .flat_map(|tensor| tensor.tensor.iter().flat_map(|row| row.to_vec()))
+        .collect();
+
+    let result = PyDict::new(py);
+    result.set_item(
+        "tensor",
+        ArrayD::from_shape_vec(tensor_shape, tensor)
+            .unwrap()
+            .into_pyarray(py),
+    )?;
+    result.set_item(
+        "isotropic",
+        values(ShieldingTensor::isotropic).into_pyarray(py),
+    )?;
+    result.set_item(
+        "anisotropy",
+        values(ShieldingTensor::anisotropy).into_pyarray(py),
+    )?;
+    result.set_item("zz", values(ShieldingTensor::zz).into_pyarray(py))?;
+    result.set_item("convention", convention.to_string())?;
+    Ok(result)
+}
+
+/// Calculates the magnetization of a current density, J
+///
+/// Parameters
+/// ----------
+/// center : ndarray
+///     Array of x-, y-, z- coordinates where the magnetization is calculated
+/// jx : ndarray
+///     Values of Jx on a 3D grid. Has to be a float64 array of size MxNxK.
+/// jy : ndarray
+///     Values of Jy on a 3D grid. Has to be a float64 array of size MxNxK.
+/// jz : ndarray
+///     Values of Jz on a 3D grid. Has to be a float64 array of size MxNxK.
+/// x_cor : ndarray
+///     X coordinates for the first dimension of the J values grid.
+/// y_cor : ndarray
+///     Y coordinates for the second dimension of the J values grid.
+/// z_cor : ndarray
+///     Z coordinates for the third dimension of the J values grid.
+/// units : str, optional
+///     Unit system of the input and the results, one of 'au' (default), 'si'
+///     or 'gaussian'.
+/// convention : str, optional
+///     Sign convention of J, 'current' (default) for the electric current
+///     density, or 'electron_flux' for the flux of the electrons, its negative.
+/// density : bool, optional
+///     Also return the local magnetization density, M = k r × J, whose
+///     integral is the magnetization. False by default.
+///
+/// The magnetization is m = k ∫ r × J dV, with k = 1/2, or 1/2c for Gaussian
+/// units, so it points along z for a current circulating counterclockwise
+/// around z, like B at its center. Versions before 0.2 returned its negative,
+/// here and in biot.
+///
+/// Returns
+/// -------
+/// m : Results
+///     The magnetization as an array of length 3, or with density=True a tuple
+///     of it and Mx, My and Mz, each of size MxNxK, in units of the
+///     magnetization per volume, with the sign convention of J as its
+///     convention attribute.
+#[pyfunction(units = "\"au\"", convention = "\"current\"", density = "false")]
+fn magnetization(
+    py: Python,
+    center: &PyArray1<f64>,
+    jx: &PyArray3<f64>,
+    jy: &PyArray3<f64>,
+    jz: &PyArray3<f64>,
+    x_cor: &PyArray1<f64>,
+    y_cor: &PyArray1<f64>,
+    z_cor: &PyArray1<f64>,
+    units: &str,
+    convention: &str,
+    density: bool,
+) -> PyResult<PyObject> {
+    let center = parse_point("center", center)?;
+    let units: Units = units.parse().map_err(exceptions::ValueError::py_err)?;
+    let convention = parse_convention(convention)?;
+    let solver = new_solver(jx, jy, jz, x_cor, y_cor, z_cor)?
+        .units(units)
+        .convention(convention);
+
+    if !density {
+        let m = solver.magnetization(center);
+        return results(py, m.to_vec().into_pyarray(py), convention);
+    }
+    let (m, (m_x, m_y, m_z)) = solver.magnetization_density(center);
+    let m = (
+        m.to_vec().into_pyarray(py),
+        m_x.into_pyarray(py),
+        m_y.into_pyarray(py),
+        m_z.into_pyarray(py),
+    );
+    results(py, m, convention)
+}
+
+/// Calculates the magnetic multipole moments of a current density, J
+///
+/// Parameters
+/// ----------
+/// center : ndarray
+///     Array of x-, y-, z- coordinates where the moments are calculated
+/// jx, jy, jz, x_cor, y_cor, z_cor
+///     Current density on a 3D grid, and its coordinates, as for magnetization.
+/// units : str, optional
+///     Unit system of the input and the results, one of 'au' (default), 'si'
+///     or 'gaussian'.
+/// convention : str, optional
+///     Sign convention of J, 'current' (default) for the electric current
+///     density, or 'electron_flux' for the flux of the electrons, its negative.
+///
+/// With k = 1/2, or 1/2c for Gaussian units, and r measured from center, the
+/// dipole is m = k ∫ r × J dV, the quadrupole
+/// Q_ab = 2k/3 ∫ [(r × J)_a r_b + r_a (r × J)_b] dV, and the toroidal dipole
+/// T = k/5 ∫ [(r · J) r - 2 r² J] dV.
+///
+/// The moments depend on the center unless the lower ones vanish: m if the
+/// net current, ∫ J dV, does not vanish, and Q and T if m does not either.
+///
+/// Returns
+/// -------
+/// dict
+///     The 'center', the 'net_current', the 'dipole', the 3x3 'quadrupole' and
+///     the 'toroidal' moment, and the sign 'convention' of J.
+#[pyfunction(units = "\"au\"", convention = "\"current\"")]
+fn multipoles(
+    py: Python,
+    center: &PyArray1<f64>,
+    jx: &PyArray3<f64>,
+    jy: &PyArray3<f64>,
+    jz: &PyArray3<f64>,
+    x_cor: &PyArray1<f64>,
+    y_cor: &PyArray1<f64>,
+    z_cor: &PyArray1<f64>,
+    units: &str,
+    convention: &str,
+) -> PyResult<PyObject> {
+    let center = parse_point("center", center)?;
+    let units: Units = units.parse().map_err(exceptions::ValueError::py_err)?;
+    let convention = parse_convention(convention)?;
+    let moments = new_solver(jx, jy, jz, x_cor, y_cor, z_cor)?
+        .units(units)
+        .convention(convention)
+        .multipoles(center);
+    let quadrupole = Array2::from_shape_fn((3, 3), |(a, b)| moments.quadrupole[a][b]);
+
+    let result = PyDict::new(py);
+    result.set_item("center", moments.center.to_vec().into_pyarray(py))?;
+    result.set_item("net_current", moments.net_current.to_vec().into_pyarray(py))?;
+    result.set_item("dipole", moments.dipole.to_vec().into_pyarray(py))?;
+    result.set_item("quadrupole", quadrupole.into_pyarray(py))?;
+    result.set_item("toroidal", moments.toroidal.to_vec().into_pyarray(py))?;
+    result.set_item("convention", convention.to_string())?;
+    Ok(result.to_object(py))
+}
+
+/// Calculates the magnetizability tensor
+///
+/// Parameters
+/// ----------
+/// center : ndarray
+///     Array of x-, y-, z- coordinates where the magnetizations are calculated
+/// jx : ndarray
+///     Values of Jx induced by unit external fields along x, y and z. Has to be
+///     a float64 array of size 3xMxNxK, where jx[b] is the response to the
+///     field along b.
+/// jy : ndarray
+///     Values of Jy, as jx.
+/// jz : ndarray
+///     Values of Jz, as jx.
+/// x_cor : ndarray
+///     X coordinates for the first dimension of the J values grid.
+/// y_cor : ndarray
+///     Y coordinates for the second dimension of the J values grid.
+/// z_cor : ndarray
+///     Z coordinates for the third dimension of the J values grid.
+/// units : str, optional
+///     Unit system of the input and the results, one of 'au' (default), 'si'
+///     or 'gaussian'.
+/// convention : str, optional
+///     Sign convention of J, 'current' (default) for the electric current
+///     density, or 'electron_flux' for the flux of the electrons, its negative.
+///
+/// J has to be the response to an external field of 1 in the chosen units.
+/// The magnetizability is then in a.u., J/T² or erg/G² respectively.
+///
+/// Returns
+/// -------
+/// dict
+///     'tensor', the 3x3 magnetizability, where tensor[a, b] is the
+///     magnetization along a per unit external field along b, its 'isotropic'
+///     value, 'anisotropic_part', the traceless symmetric part, 'anisotropy',
+///     ξ33 - (ξ11 + ξ22) / 2, and 'eigenvalues' in ascending order with the
+///     'eigenvectors' as rows, of its symmetric part, and the sign 'convention'
+///     of J.
+#[pyfunction(units = "\"au\"", convention = "\"current\"")]
+fn magnetizability(
+    py: Python,
+    center: &PyArray1<f64>,
+    jx: &PyArray4<f64>,
+    jy: &PyArray4<f64>,
+    jz: &PyArray4<f64>,
+    x_cor: &PyArray1<f64>,
+    y_cor: &PyArray1<f64>,
+    z_cor: &PyArray1<f64>,
+    units: &str,
+    convention: &str,
+) -> PyResult<PyObject> {
+    let center = parse_point("center", center)?;
+    let units: Units = units.parse().map_err(exceptions::ValueError::py_err)?;
+    let convention = parse_convention(convention)?;
+    let [x, y, z] = response_solvers(jx, jy, jz, x_cor, y_cor, z_cor, |solver| {
+        solver.units(units).convention(convention)
+    })?;
+    let tensor = MagnetizabilityTensor::new([&x, &y, &z], center);
+    let (eigenvalues, eigenvectors) = tensor.eigen();
+    let matrix = |m: [[f64; 3]; 3]| Array2::from_shape_fn((3, 3), |(a, b)| m[a][b]);
+
+    let result = PyDict::new(py);
+    result.set_item("tensor", matrix(tensor.tensor).into_pyarray(py))?;
+    result.set_item("isotropic", tensor.isotropic())?;
+    result.set_item(
+        "anisotropic_part",
+        matrix(tensor.anisotropic_part()).into_pyarray(py),
+    )?;
+    result.set_item("anisotropy", tensor.anisotropy())?;
+    result.set_item("eigenvalues", eigenvalues.to_vec().into_pyarray(py))?;
+    result.set_item("eigenvectors", matrix(eigenvectors).into_pyarray(py))?;
+    result.set_item("convention", convention.to_string())?;
+    Ok(result.to_object(py))
+}
+
+/// Integrates the current through a half-plane, as the ring-current strength of
+/// GIMIC
+///
+/// Parameters
+/// ----------
+/// origin : ndarray
+///     Point on the edge of the half-plane, such as the center of a ring.
+/// direction : ndarray
+///     Direction the half-plane extends in from its edge, such as from the
+///     center of a ring through the middle of a bond.
+/// up : ndarray
+///     Direction of the external field, along the edge of the half-plane.
+/// length : float
+///     Extent of the half-plane along direction.
+/// height : float
+///     Extent of the half-plane above and below origin along up.
+/// spacing : float
+///     Largest distance between the integration points.
+/// jx : ndarray
+///     Values of Jx induced by a unit external field along up, on a 3D grid.
+///     Has to be a float64 array of size MxNxK.
+/// jy : ndarray
+///     Values of Jy, as jx.
+/// jz : ndarray
+///     Values of Jz, as jx.
+/// x_cor : ndarray
+///     X coordinates for the first dimension of the J values grid.
+/// y_cor : ndarray
+///     Y coordinates for the second dimension of the J values grid.
+/// z_cor : ndarray
+///     Z coordinates for the third dimension of the J values grid.
+/// units : str, optional
+///     Unit system of the input, one of 'au' (default), 'si' or 'gaussian'.
+/// convention : str, optional
+///     Sign convention of J, 'current' (default) for the electric current
+///     density, or 'electron_flux' for the flux of the electrons, its negative.
+///
+/// J is interpolated trilinearly between the grid points, and taken to be zero
+/// outside of the grid.
+///
+/// Raises
+/// ------
+/// BiotSavartError
+///     If length, height or spacing is not positive, or direction and up are
+///     parallel.
+///
+/// Returns
+/// -------
+/// dict
+///     The 'total' current in nA/T, the sum of the positive 'diatropic' and the
+///     negative 'paratropic' contributions, where diatropic currents run along
+///     direction x up, and the 'profile' of the current per unit length along
+///     direction at 'distances' from origin, and the sign 'convention' of J.
+#[pyfunction(units = "\"au\"", convention = "\"current\"")]
+fn current_strength(
+    py: Python,
+    origin: &PyArray1<f64>,
+    direction: &PyArray1<f64>,
+    up: &PyArray1<f64>,
+    length: f64,
+    height: f64,
+    spacing: f64,
+    jx: &PyArray3<f64>,
+    jy: &PyArray3<f64>,
+    jz: &PyArray3<f64>,
+    x_cor: &PyArray1<f64>,
+    y_cor: &PyArray1<f64>,
+    z_cor: &PyArray1<f64>,
+    units: &str,
+    convention: &str,
+) -> PyResult<PyObject> {
+    let plane = HalfPlane {
+        origin: parse_point("origin", origin)?,
+        direction: parse_point("direction", direction)?,
+        up: parse_point("up", up)?,
+        length,
+        height,
+        spacing,
+    };
+    let units: Units = units.parse().map_err(exceptions::ValueError::py_err)?;
+    let convention = parse_convention(convention)?;
+    let strength = new_solver(jx, jy, jz, x_cor, y_cor, z_cor)?
+        .units(units)
+        .convention(convention)
+        .current_strength(&plane)?;
+
+    let result = PyDict::new(py);
+    result.set_item("total", strength.total)?;
+    result.set_item("diatropic", strength.diatropic)?;
+    result.set_item("paratropic", strength.paratropic)?;
+    result.set_item("distances", strength.distances.into_pyarray(py))?;
+    result.set_item("profile", strength.profile.into_pyarray(py))?;
+    result.set_item("convention", convention.to_string())?;
+    Ok(result.to_object(py))
+}
+
+/// Calculates the magnetic field, B, generated by a current density, J, on a
+/// separate target grid
+///
+/// Parameters
+/// ----------
+/// target_x : ndarray
+///     X coordinates for the first dimension of the target grid.
+/// target_y : ndarray
+///     Y coordinates for the second dimension of the target grid.
+/// target_z : ndarray
+///     Z coordinates for the third dimension of the target grid.
+/// jx : ndarray
+///     Values of Jx on a 3D grid. Has to be a float64 array of size MxNxK.
+/// jy : ndarray
+///     Values of Jy on a 3D grid. Has to be a float64 array of size MxNxK.
+/// jz : ndarray
+///     Values of Jz on a 3D grid. Has to be a float64 array of size MxNxK.
+/// x_cor : ndarray
+///     X coordinates for the first dimension of the J values grid.
+/// y_cor : ndarray
+///     Y coordinates for the second dimension of the J values grid.
+/// z_cor : ndarray
+///     Z coordinates for the third dimension of the J values grid.
+/// units : str, optional
+///     Unit system of the input and the results, one of 'au' (default), 'si'
+///     or 'gaussian'.
+/// solver : str, optional
+///     'direct' (default) or 'tree'.
+/// theta : float, optional
+///     Opening angle of the tree code, 0.5 by default. Smaller is more accurate.
+/// singularity : str, optional
+///     Treatment of targets at grid points of J. 'exclude' (default) leaves the
+///     grid point out, 'analytic' integrates its voxel with J to first order,
+///     and 'soften' uses the kernel r / (r² + softening²)^(3/2).
+/// softening : float, optional
+///     Softening length used with singularity='soften', which can also be
+///     given as singularity='soften:<length>'.
+/// backend : str, optional
+///     Instruction set of the direct sum, one of 'auto' (default), 'scalar',
+///     'sse2', 'sse41' or 'avx2'. Forcing one makes results reproducible
+///     across CPUs.
+/// cancel : CancelToken, optional
+///     Token to stop the calculation from another thread.
+/// progress : callable, optional
+///     Called as progress(done, total) while the calculation runs, with the
+///     number of completed slabs of targets and the total number of slabs.
+/// convention : str, optional
+///     Sign convention of J, 'current' (default) for the electric current
+///     density, or 'electron_flux' for the flux of the electrons, its negative.
+///
+/// A plane or a line is calculated by giving a single target coordinate along
+/// the other dimensions.
+///
+/// Returns
+/// -------
+/// B : Results
+///     tuple of Bx, By and Bz, each of size len(target_x)xlen(target_y)xlen(target_z),
+///     with the sign convention of J as its convention attribute.
+#[pyfunction(
+    units = "\"au\"",
+    solver = "\"direct\"",
+    theta = "0.5",
+    singularity = "\"exclude\"",
+    softening = "0.0",
+    backend = "\"auto\"",
+    cancel = "None",
+    progress = "None",
+    convention = "\"current\""
+)]
+fn biot_grid(
+    py: Python,
+    target_x: &PyArray1<f64>,
+    target_y: &PyArray1<f64>,
+    target_z: &PyArray1<f64>,
+    jx: &PyArray3<f64>,
+    jy: &PyArray3<f64>,
+    jz: &PyArray3<f64>,
+    x_cor: &PyArray1<f64>,
+    y_cor: &PyArray1<f64>,
+    z_cor: &PyArray1<f64>,
+    units: &str,
+    solver: &str,
+    theta: f64,
+    singularity: &str,
+    softening: f64,
+    backend: &str,
+    cancel: Option<&PyCancelToken>,
+    progress: Option<PyObject>,
+    convention: &str,
+) -> PyResult<PyObject> {
+    let (units, method) = parse_options(units, solver, theta)?;
+    let singularity = parse_singularity(singularity, softening)?;
+    let convention = parse_convention(convention)?;
+
+    let target_x = as_slice("target_x", target_x)?;
+    let target_y = as_slice("target_y", target_y)?;
+    let target_z = as_slice("target_z", target_z)?;
+    let cancel = cancel_token(cancel);
+    let progress = PyProgress::new(progress);
+
+    let solver = new_solver(jx, jy, jz, x_cor, y_cor, z_cor)?
+        .units(units)
+        .convention(convention)
+        .solver(method)
+        .singularity(singularity)
+        .backend(parse_backend(backend)?)
+        .cancel_token(cancel.clone())
+        .progress(&progress);
+
+    let (b_x, b_y, b_z) = run(py, &cancel, &progress, || {
+        solver.field_on_grid(target_x, target_y, target_z)
+    })?;
+
+    let b = (
+        b_x.into_pyarray(py).to_owned(),
+        b_y.into_pyarray(py).to_owned(),
+        b_z.into_pyarray(py).to_owned(),
+    );
+    results(py, b, convention)
+}
+
+/// Estimates the error of a solver compared to the direct sum
+///
+/// Parameters
+/// ----------
+/// jx : ndarray
+///     Values of Jx on a 3D grid. Has to be a float64 array of size MxNxK.
+/// jy : ndarray
+///     Values of Jy on a 3D grid. Has to be a float64 array of size MxNxK.
+/// jz : ndarray
+///     Values of Jz on a 3D grid. Has to be a float64 array of size MxNxK.
+/// x_cor : ndarray
+///     X coordinates for the first dimension of the J values grid.
+/// y_cor : ndarray
+///     Y coordinates for the second dimension of the J values grid.
+/// z_cor : ndarray
+///     Z coordinates for the third dimension of the J values grid.
+/// solver : str, optional
+///     'tree' (default) or 'fft'.
+/// theta : float, optional
+///     Opening angle of the tree code, 0.5 by default.
+/// samples : int, optional
+///     Number of grid points B is compared at, 100 by default.
+/// backend : str, optional
+///     Instruction set of the direct sum, one of 'auto' (default), 'scalar',
+///     'sse2', 'sse41' or 'avx2'. Forcing one makes results reproducible
+///     across CPUs.
+/// cancel : CancelToken, optional
+///     Token to stop the calculation from another thread.
+/// progress : callable, optional
+///     Called as progress(done, total) while the calculation runs, with the
+///     number of completed slabs of targets and the total number of slabs.
+///
+/// Returns
+/// -------
+/// error : dict
+///     'max_abs', the largest error of |B| in atomic units, 'max_rel', max_abs
+///     relative to the largest |B|, and 'rms_rel', the relative root mean square
+///     error.
+#[pyfunction(
+    solver = "\"tree\"",
+    theta = "0.5",
+    samples = "100",
+    backend = "\"auto\"",
+    cancel = "None",
+    progress = "None"
+)]
+fn biot_error(
+    py: Python,
+    jx: &PyArray3<f64>,
+    jy: &PyArray3<f64>,
+    jz: &PyArray3<f64>,
+    x_cor: &PyArray1<f64>,
+    y_cor: &PyArray1<f64>,
+    z_cor: &PyArray1<f64>,
+    solver: &str,
+    theta: f64,
+    samples: usize,
+    backend: &str,
+    cancel: Option<&PyCancelToken>,
+    progress: Option<PyObject>,
+) -> PyResult<PyObject> {
+    let (_, method) = parse_options("au", solver, theta)?;
+
+    let cancel = cancel_token(cancel);
+    let progress = PyProgress::new(progress);
+
+    let solver = new_solver(jx, jy, jz, x_cor, y_cor, z_cor)?
+        .solver(method)
+        .backend(parse_backend(backend)?)
+        .cancel_token(cancel.clone())
+        .progress(&progress);
+    let error = run(py, &cancel, &progress, || solver.estimate_error(samples))?;
+
+    let result = PyDict::new(py);
+    result.set_item("max_abs", error.max_abs)?;
+    result.set_item("max_rel", error.max_rel)?;
+    result.set_item("rms_rel", error.rms_rel)?;
+    Ok(result.to_object(py))
+}
+
+/// Returns the instruction set the direct sum uses with backend='auto' on
+/// this CPU
+#[pyfunction]
+fn simd_backend() -> String {
+    Backend::detect().to_string()
+}
+
+#[pymodule]
+fn libbiot_savart(py: Python, m: &PyModule) -> PyResult<()> {
+    // fails if the embedding application has installed a logger already
+    if log::set_boxed_logger(Box::new(PythonLogger::default())).is_ok() {
+        log::set_max_level(LevelFilter::Debug);
+    }
+
+    m.add_wrapped(wrap_pyfunction!(biot))?;
+    m.add_wrapped(wrap_pyfunction!(biot_points))?;
+    m.add_wrapped(wrap_pyfunction!(biot_grid))?;
+    m.add_wrapped(wrap_pyfunction!(biot_error))?;
+    m.add_wrapped(wrap_pyfunction!(biot_cube))?;
+    m.add_wrapped(wrap_pyfunction!(shielding))?;
+    m.add_wrapped(wrap_pyfunction!(shielding_line))?;
+    m.add_wrapped(wrap_pyfunction!(shielding_plane))?;
+    m.add_wrapped(wrap_pyfunction!(magnetization))?;
+    m.add_wrapped(wrap_pyfunction!(multipoles))?;
+    m.add_wrapped(wrap_pyfunction!(magnetizability))?;
+    m.add_wrapped(wrap_pyfunction!(current_strength))?;
+    m.add_wrapped(wrap_pyfunction!(read_cube))?;
+    m.add_wrapped(wrap_pyfunction!(read_gimic))?;
+    m.add_wrapped(wrap_pyfunction!(write_cubes))?;
+    m.add_wrapped(wrap_pyfunction!(write_vtk))?;
+    m.add_wrapped(wrap_pyfunction!(simd_backend))?;
+    m.add_class::<PyCancelToken>()?;
+    m.add_class::<PyResults>()?;
+    m.add("__version__", env!("CARGO_PKG_VERSION"))?;
+
+    m.add("BiotSavartError", py.get_type::<BiotSavartError>())?;
+    m.add("ShapeMismatchError", py.get_type::<ShapeMismatchError>())?;
+    m.add("EmptyGridError", py.get_type::<EmptyGridError>())?;
+    m.add("NonMonotonicError", py.get_type::<NonMonotonicError>())?;
+    m.add("NonFiniteError", py.get_type::<NonFiniteError>())?;
+    m.add("NonUniformGridError", py.get_type::<NonUniformGridError>())?;
+    m.add(
+        "UnsupportedBackendError",
+        py.get_type::<UnsupportedBackendError>(),
+    )?;
+    m.add("InvalidFileError", py.get_type::<InvalidFileError>())?;
+    m.add("CancelledError", py.get_type::<CancelledError>())?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pyo3::types::PyList;
+
+    // Calculation that runs until it is cancelled, after reporting progress
+    fn until_cancelled(cancel: &CancelToken, progress: &PyProgress) -> Result<()> {
+        progress.report(0, 1);
+        while !cancel.is_cancelled() {
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        Err(Error::Cancelled)
+    }
+
+    #[test]
+    fn run_returns_the_result() {
+        let gil = Python::acquire_gil();
+        let py = gil.python();
+        let cancel = cancel_token(None);
+        let progress = PyProgress::new(None);
+
+        assert_eq!(run(py, &cancel, &progress, || Ok(42)).unwrap(), 42);
+        let err = run(py, &cancel, &progress, || -> Result<()> {
+            Err(Error::NonUniform)
+        })
+        .unwrap_err();
+        assert!(err.is_instance::<NonUniformGridError>(py));
+    }
+
+    #[test]
+    fn run_raises_cancelled_error() {
+        let gil = Python::acquire_gil();
+        let py = gil.python();
+        let caller = PyCancelToken {
+            token: CancelToken::new(),
+        };
+        let cancel = cancel_token(Some(&caller));
+        let progress = PyProgress::new(None);
+
+        let err = run(py, &cancel, &progress, || {
+            caller.token.cancel();
+            until_cancelled(&cancel, &progress)
+        })
+        .unwrap_err();
+        assert!(err.is_instance::<CancelledError>(py));
+    }
+
+    #[test]
+    fn run_cancels_on_exception_in_progress() {
+        let gil = Python::acquire_gil();
+        let py = gil.python();
+        let caller = PyCancelToken {
+            token: CancelToken::new(),
+        };
+        let cancel = cancel_token(Some(&caller));
+        let callback = py.eval("lambda done, total: 1 / 0", None, None).unwrap();
+        let progress = PyProgress::new(Some(callback.to_object(py)));
+
+        // the exception of the callable is raised, not CancelledError
+        let err = run(py, &cancel, &progress, || {
+            until_cancelled(&cancel, &progress)
+        })
+        .unwrap_err();
+        assert!(err.is_instance::<exceptions::ZeroDivisionError>(py));
+        assert!(cancel.is_cancelled());
+        assert!(!caller.token.is_cancelled());
+    }
+
+    #[test]
+    fn run_reports_the_final_progress() {
+        let gil = Python::acquire_gil();
+        let py = gil.python();
+        let reports = PyList::empty(py);
+        let globals = PyDict::new(py);
+        globals.set_item("reports", reports).unwrap();
+        let callback = py
+            .eval(
+                "lambda done, total: reports.append(done)",
+                Some(globals),
+                None,
+            )
+            .unwrap();
+        let cancel = cancel_token(None);
+        let progress = PyProgress::new(Some(callback.to_object(py)));
+
+        run(py, &cancel, &progress, || {
+            progress.report(0, 2);
+            progress.report(2, 2);
+            Ok(())
+        })
+        .unwrap();
+        let reports: Vec<usize> = reports.extract().unwrap();
+        assert_eq!(reports.last(), Some(&2));
+    }
+
+    #[test]
+    fn errors_map_to_exceptions() {
+        let gil = Python::acquire_gil();
+        let py = gil.python();
+        let input_errors = vec![
+            Error::ShapeMismatch {
+                name: "jx",
+                expected: vec![2],
+                found: vec![3],
+            },
+            Error::EmptyGrid("x_cor"),
+            Error::NonMonotonic("x_cor"),
+            Error::NonFinite("jx"),
+            Error::NonUniform,
+            Error::FftOffGrid,
+            Error::UnsupportedBackend(Backend::Avx2),
+            Error::InvalidFile {
+                path: "j.cube".to_string(),
+                message: "no atoms".to_string(),
+            },
+            Error::InvalidPlane("no normal"),
+            Error::InvalidTheta(-1.0),
+        ];
+        for error in input_errors {
+            let err = PyErr::from(error.clone());
+            assert!(err.is_instance::<BiotSavartError>(py), "{:?}", error);
+            assert!(err.is_instance::<exceptions::ValueError>(py), "{:?}", error);
+        }
+
+        let err = PyErr::from(Error::ShapeMismatch {
+            name: "jx",
+            expected: vec![2],
+            found: vec![3],
+        });
+        assert!(err.is_instance::<ShapeMismatchError>(py));
+        assert!(PyErr::from(Error::EmptyGrid("x_cor")).is_instance::<EmptyGridError>(py));
+        assert!(PyErr::from(Error::NonMonotonic("x_cor")).is_instance::<NonMonotonicError>(py));
+        assert!(PyErr::from(Error::NonFinite("jx")).is_instance::<NonFiniteError>(py));
+        assert!(PyErr::from(Error::NonUniform).is_instance::<NonUniformGridError>(py));
+        assert!(PyErr::from(Error::UnsupportedBackend(Backend::Avx2))
+            .is_instance::<UnsupportedBackendError>(py));
+        let err = PyErr::from(Error::InvalidFile {
+            path: "j.cube".to_string(),
+            message: "no atoms".to_string(),
+        });
+        assert!(err.is_instance::<InvalidFileError>(py));
+
+        let err = PyErr::from(Error::Cancelled);
+        assert!(err.is_instance::<CancelledError>(py));
+        assert!(err.is_instance::<exceptions::RuntimeError>(py));
+        assert!(!err.is_instance::<BiotSavartError>(py));
+        let err = PyErr::from(Error::Io("j.cube: not found".to_string()));
+        assert!(err.is_instance::<exceptions::OSError>(py));
+        assert!(!err.is_instance::<BiotSavartError>(py));
+    }
+
+    #[test]
+    fn results_unpack_like_their_values() {
+        let gil = Python::acquire_gil();
+        let py = gil.python();
+        let globals = PyDict::new(py);
+        let results = results(py, (1, 2.5), Convention::ElectronFlux).unwrap();
+        globals.set_item("results", results).unwrap();
+        py.run(
+            "a, b = results\n\
+             assert (a, b) == (1, 2.5) == results.values\n\
+             assert len(results) == 2 and results[-1] == 2.5\n\
+             assert results.convention == 'electron_flux'",
+            Some(globals),
+            None,
+        )
+        .unwrap();
+    }
+}
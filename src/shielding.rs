@@ -0,0 +1,189 @@
+use ndarray::prelude::*;
+
+use crate::error::{Error, Result};
+use crate::solver::BiotSavart;
+use crate::tensor::{symmetric_eigen, symmetric_part};
+
+// Parts per million
+const PPM: f64 = 1e6;
+
+/// Magnetic shielding tensor at a point, in ppm.
+///
+/// `tensor[a][b]` is σ_ab = -B_ind,a / B_0,b, the induced field along `a` per
+/// unit external field along `b`, with its sign flipped so that shielding is
+/// positive. The nucleus-independent chemical shift is NICS = -σ_iso, and
+/// NICS_zz = -σ_zz.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ShieldingTensor {
+    pub tensor: [[f64; 3]; 3],
+}
+
+impl ShieldingTensor {
+    /// Returns σ_iso, a third of the trace.
+    pub fn isotropic(&self) -> f64 {
+        (self.tensor[0][0] + self.tensor[1][1] + self.tensor[2][2]) / 3.0
+    }
+
+    /// Returns the principal components σ_11 ≤ σ_22 ≤ σ_33, the eigenvalues of
+    /// the symmetric part of the tensor. The antisymmetric part does not
+    /// contribute to the shielding observed in solution.
+    pub fn principal(&self) -> [f64; 3] {
+        symmetric_eigen(symmetric_part(self.tensor)).0
+    }
+
+    /// Returns the anisotropy, Δσ = σ_33 - (σ_11 + σ_22) / 2, of the principal
+    /// components in ascending order.
+    pub fn anisotropy(&self) -> f64 {
+        let [s11, s22, s33] = self.principal();
+        s33 - 0.5 * (s11 + s22)
+    }
+
+    /// Returns σ_zz, the shielding along z for a field along z.
+    pub fn zz(&self) -> f64 {
+        self.tensor[2][2]
+    }
+}
+
+/// Shielding tensors at points along a line, as returned by
+/// [`Shielding::line`](struct.Shielding.html#method.line).
+#[derive(Clone, Debug, PartialEq)]
+pub struct LineScan {
+    /// Distance of each point from the start of the line
+    pub distances: Vec<f64>,
+    /// Nx3 array of the coordinates of the points
+    pub points: Array2<f64>,
+    pub tensors: Vec<ShieldingTensor>,
+}
+
+/// Shielding tensors at the points of a plane, as returned by
+/// [`Shielding::plane`](struct.Shielding.html#method.plane).
+///
+/// Values over the plane are collected with `map`, e.g.
+/// `plane.tensors.map(ShieldingTensor::isotropic)`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PlaneMap {
+    /// Distance of the points from the corner along the first edge
+    pub u: Vec<f64>,
+    /// Distance of the points from the corner along the second edge
+    pub v: Vec<f64>,
+    /// Coordinates of the points, of size NuxNvx3
+    pub points: Array3<f64>,
+    /// Tensors at the points, of size NuxNv
+    pub tensors: Array2<ShieldingTensor>,
+}
+
+/// Magnetic shielding from the current densities induced by unit external
+/// fields along x, y and z.
+///
+/// Each current density is given as a solver, which sets up its grid, units and
+/// options. The current density has to be the response to an external field of
+/// 1 in its units, e.g. 1 T for SI, which makes the shielding the same in any
+/// unit system. GIMIC writes such current densities, in atomic units.
+pub struct Shielding<'a> {
+    responses: [BiotSavart<'a>; 3],
+}
+
+impl<'a> Shielding<'a> {
+    /// Takes the solvers of the current densities induced by fields along x, y
+    /// and z.
+    pub fn new(x: BiotSavart<'a>, y: BiotSavart<'a>, z: BiotSavart<'a>) -> Self {
+        Shielding {
+            responses: [x, y, z],
+        }
+    }
+
+    /// Calculates the shielding tensor at arbitrary points.
+    ///
+    /// `points` is an Nx3 array of x-, y-, z- coordinates. The induced fields
+    /// are summed with
+    /// [`BiotSavart::field_at`](struct.BiotSavart.html#method.field_at), so this
+    /// costs three times as much as B at the points.
+    pub fn at(&self, points: ArrayView2<f64>) -> Result<Vec<ShieldingTensor>> {
+        let mut tensors = vec![
+            ShieldingTensor {
+                tensor: [[0.0; 3]; 3]
+            };
+            points.nrows()
+        ];
+        for (b, response) in self.responses.iter().enumerate() {
+            let field = response.field_at(points)?;
+            for (tensor, induced) in tensors.iter_mut().zip(field.genrows()) {
+                for (row, induced) in tensor.tensor.iter_mut().zip(induced) {
+                    row[b] = -PPM * induced;
+                }
+            }
+        }
+        Ok(tensors)
+    }
+
+    /// Calculates the shielding tensor at `n` evenly spaced points from `start`
+    /// to `end`, both included.
+    ///
+    /// For a NICS scan of a ring, the line runs along the normal through the
+    /// center of the ring, where the point at 0 gives NICS(0) and the one at a
+    /// distance of 1 Å, 1.89 bohr, gives NICS(1). A single point lies at
+    /// `start`.
+    pub fn line(&self, start: [f64; 3], end: [f64; 3], n: usize) -> Result<LineScan> {
+        if n == 0 {
+            return Err(Error::EmptyGrid("line"));
+        }
+        let step = steps(n);
+        let points =
+            Array2::from_shape_fn((n, 3), |(i, a)| start[a] + step[i] * (end[a] - start[a]));
+        let length = norm(difference(end, start));
+
+        Ok(LineScan {
+            distances: step.iter().map(|t| t * length).collect(),
+            tensors: self.at(points.view())?,
+            points,
+        })
+    }
+
+    /// Calculates the shielding tensor on a plane, at `nu` by `nv` points of the
+    /// parallelogram with a corner at `corner` and edges `u` and `v`, both
+    /// included.
+    ///
+    /// For a map centered above a molecule at height h, the corner is at
+    /// (-a, -a, h) with edges (2a, 0, 0) and (0, 2a, 0).
+    pub fn plane(
+        &self,
+        corner: [f64; 3],
+        u: [f64; 3],
+        v: [f64; 3],
+        nu: usize,
+        nv: usize,
+    ) -> Result<PlaneMap> {
+        if nu == 0 || nv == 0 {
+            return Err(Error::EmptyGrid("plane"));
+        }
+        let (step_u, step_v) = (steps(nu), steps(nv));
+        let points = Array3::from_shape_fn((nu, nv, 3), |(i, j, a)| {
+            corner[a] + step_u[i] * u[a] + step_v[j] * v[a]
+        });
+        let flat = points.view().into_shape((nu * nv, 3)).unwrap();
+        let tensors = Array2::from_shape_vec((nu, nv), self.at(flat)?).unwrap();
+
+        Ok(PlaneMap {
+            u: step_u.iter().map(|t| t * norm(u)).collect(),
+            v: step_v.iter().map(|t| t * norm(v)).collect(),
+            points,
+            tensors,
+        })
+    }
+}
+
+// Returns n fractions evenly spaced from 0 to 1, or 0 for a single point.
+fn steps(n: usize) -> Vec<f64> {
+    if n == 1 {
+        return vec![0.0];
+    }
+    (0..n).map(|i| i as f64 / (n - 1) as f64).collect()
+}
+
+fn difference(a: [f64; 3], b: [f64; 3]) -> [f64; 3] {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+fn norm(a: [f64; 3]) -> f64 {
+    (a[0] * a[0] + a[1] * a[1] + a[2] * a[2]).sqrt()
+}
@@ -0,0 +1,180 @@
+use std::fmt;
+use std::str::FromStr;
+
+use ndarray::prelude::*;
+
+/// Treatment of the singularity of J × r / r³ where a target coincides with a
+/// grid point of J.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Singularity {
+    /// The grid point at the target is left out of the sum. This is the exact
+    /// integral over its voxel if J is constant within it.
+    Exclude,
+    /// The voxel around the target is integrated analytically, with J expanded
+    /// to first order around the target using finite differences on the grid.
+    /// Targets that are not grid points are treated as with `Exclude`.
+    Analytic,
+    /// The kernel is softened to J × r / (r² + ε²)^(3/2) with softening length ε,
+    /// which also smooths the field close to every grid point.
+    Soften(f64),
+}
+
+impl Singularity {
+    /// Parses a treatment as its `FromStr` implementation does, except that a
+    /// plain "soften" takes its length from `softening`, for front ends that
+    /// have the softening length as a separate setting.
+    pub fn parse(name: &str, softening: Option<f64>) -> Result<Singularity, String> {
+        if !name.trim().eq_ignore_ascii_case("soften") {
+            return name.parse();
+        }
+        match softening {
+            Some(eps) if eps > 0.0 && eps.is_finite() => Ok(Singularity::Soften(eps)),
+            _ => Err(format!("'{}' needs a positive softening", name)),
+        }
+    }
+
+    // Square of the softening length
+    pub(crate) fn eps2(self) -> f64 {
+        match self {
+            Singularity::Soften(eps) => eps * eps,
+            _ => 0.0,
+        }
+    }
+}
+
+impl Default for Singularity {
+    fn default() -> Self {
+        Singularity::Exclude
+    }
+}
+
+impl FromStr for Singularity {
+    type Err = String;
+
+    // The softening length is given after a colon, e.g. soften:0.1, as a zero
+    // length would be `Exclude` in disguise.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let lower = s.to_lowercase();
+        let mut parts = lower.splitn(2, ':');
+        match (parts.next().unwrap(), parts.next()) {
+            ("exclude", None) => Ok(Singularity::Exclude),
+            ("analytic", None) => Ok(Singularity::Analytic),
+            ("soften", Some(eps)) => match eps.trim().parse::<f64>() {
+                Ok(eps) if eps > 0.0 && eps.is_finite() => Ok(Singularity::Soften(eps)),
+                _ => Err(format!(
+                    "the softening length of '{}' has to be a positive number",
+                    s
+                )),
+            },
+            ("soften", None) => Err(format!(
+                "'{}' needs a softening length, e.g. 'soften:0.1'",
+                s
+            )),
+            _ => Err(format!(
+                "unknown singularity treatment '{}', expected 'exclude', 'analytic' or 'soften:<length>'",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Singularity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Singularity::Exclude => write!(f, "exclude"),
+            Singularity::Analytic => write!(f, "analytic"),
+            Singularity::Soften(eps) => write!(f, "soften({})", eps),
+        }
+    }
+}
+
+// Returns the index of the grid coordinate equal to `value`, if any.
+pub(crate) fn grid_index(cor: &[f64], widths: &[f64], value: f64) -> Option<usize> {
+    cor.iter()
+        .zip(widths)
+        .position(|(c, w)| (c - value).abs() <= 1e-10 * w)
+}
+
+// ∫ s_a² / |s|³ over the box [-a, a] x [-b, b] x [-c, c], for each direction a.
+//
+// Uses s_x² / |s|³ = 1 / |s| - ∂/∂x (x / |s|), where both terms have closed
+// forms over a box.
+fn voxel_coefficients(a: f64, b: f64, c: f64) -> [f64; 3] {
+    let inverse_r = 8.0 * box_inverse_r(a, b, c);
+    [
+        inverse_r - 8.0 * a * face_inverse_r(a, b, c),
+        inverse_r - 8.0 * b * face_inverse_r(b, a, c),
+        inverse_r - 8.0 * c * face_inverse_r(c, a, b),
+    ]
+}
+
+// ∫ 1 / |s| over [0, a] x [0, b] x [0, c]
+fn box_inverse_r(a: f64, b: f64, c: f64) -> f64 {
+    let r = (a * a + b * b + c * c).sqrt();
+    b * c * (a / (b * b + c * c).sqrt()).asinh()
+        + a * c * (b / (a * a + c * c).sqrt()).asinh()
+        + a * b * (c / (a * a + b * b).sqrt()).asinh()
+        - 0.5 * a * a * (b * c / (a * r)).atan()
+        - 0.5 * b * b * (a * c / (b * r)).atan()
+        - 0.5 * c * c * (a * b / (c * r)).atan()
+}
+
+// ∫ 1 / √(a² + y² + z²) over [0, b] x [0, c]
+fn face_inverse_r(a: f64, b: f64, c: f64) -> f64 {
+    let r = (a * a + b * b + c * c).sqrt();
+    b * (c / (a * a + b * b).sqrt()).asinh() + c * (b / (a * a + c * c).sqrt()).asinh()
+        - a * (b * c / (a * r)).atan()
+}
+
+// Derivative of J along `axis` at `idx`, by central differences inside the grid
+// and one-sided differences at its edges.
+fn derivative(j: &ArrayView3<f64>, cor: &[f64], axis: usize, idx: [usize; 3]) -> f64 {
+    let n = cor.len();
+    if n < 2 {
+        return 0.0;
+    }
+    let i = idx[axis];
+    let (lower, upper) = (i.saturating_sub(1), (i + 1).min(n - 1));
+
+    let mut lower_idx = idx;
+    let mut upper_idx = idx;
+    lower_idx[axis] = lower;
+    upper_idx[axis] = upper;
+    (j[upper_idx] - j[lower_idx]) / (cor[upper] - cor[lower])
+}
+
+// Integral of J × r / r³ over the voxel around the grid point `idx`, with J
+// expanded to first order around the point. With s = r' - r the offset of the
+// source from the target, this is -Σ_a ∂_a J × e_a ∫ s_a² / |s|³.
+pub(crate) fn voxel_field(
+    j: [&ArrayView3<f64>; 3],
+    cor: [&[f64]; 3],
+    widths: [&[f64]; 3],
+    idx: [usize; 3],
+) -> [f64; 3] {
+    let coefficients = voxel_coefficients(
+        0.5 * widths[0][idx[0]],
+        0.5 * widths[1][idx[1]],
+        0.5 * widths[2][idx[2]],
+    );
+
+    let mut result = [0f64; 3];
+    for axis in 0..3 {
+        let d = [
+            derivative(j[0], cor[axis], axis, idx),
+            derivative(j[1], cor[axis], axis, idx),
+            derivative(j[2], cor[axis], axis, idx),
+        ];
+        // d × e_axis
+        let cross = match axis {
+            0 => [0.0, d[2], -d[1]],
+            1 => [-d[2], 0.0, d[0]],
+            _ => [d[1], -d[0], 0.0],
+        };
+        for a in 0..3 {
+            result[a] -= coefficients[axis] * cross[a];
+        }
+    }
+
+    result
+}
@@ -0,0 +1,687 @@
+use std::fmt;
+use std::str::FromStr;
+
+use log::{debug, info};
+use ndarray::prelude::*;
+use ndarray::Zip;
+
+use crate::cancel::CancelToken;
+use crate::convention::Convention;
+use crate::current::{current_strength, CurrentStrength, HalfPlane};
+use crate::error::{check_coordinates, check_finite, check_shape, Error, Result};
+use crate::fft::{convolve, uniform_spacing, CONVOLUTION_STAGES};
+use crate::grid::cell_widths;
+use crate::kernel::{kernel, Backend, Kernel};
+use crate::multipole::{multipoles, Multipoles};
+use crate::progress::{Progress, Tally};
+use crate::singularity::{grid_index, voxel_field, Singularity};
+use crate::tree::Tree;
+use crate::units::Units;
+
+// Number of points along z passed to the kernel at a time
+const CHUNK: usize = 64;
+
+/// Method used to calculate the magnetic field on the grid of J.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Solver {
+    /// Direct summation over all grid points, O(N²) in the number of points.
+    Direct,
+    /// Convolution by FFT, O(N log N) in the number of points. Requires a
+    /// uniformly spaced grid, and only applies to the grid of J itself, other
+    /// targets return [`Error::FftOffGrid`](enum.Error.html#variant.FftOffGrid).
+    Fft,
+    /// Barnes-Hut tree code with opening angle theta, O(N log N) in the number
+    /// of points. Works for any grid and any target points. Cells of the grid
+    /// with radius < theta * distance to the target are approximated by their
+    /// current and current dipole, so a smaller theta is more accurate and
+    /// theta = 0 is the direct sum. Values between 0.3 and 0.7 are typical, a
+    /// negative or NaN theta fails with `Error::InvalidTheta`.
+    Tree(f64),
+}
+
+/// Default opening angle of the tree code
+pub const DEFAULT_THETA: f64 = 0.5;
+
+impl Default for Solver {
+    fn default() -> Self {
+        Solver::Direct
+    }
+}
+
+impl FromStr for Solver {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "direct" => Ok(Solver::Direct),
+            "fft" => Ok(Solver::Fft),
+            "tree" => Ok(Solver::Tree(DEFAULT_THETA)),
+            _ => Err(format!(
+                "unknown solver '{}', expected 'direct', 'fft' or 'tree'",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Solver {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Solver::Direct => write!(f, "direct"),
+            Solver::Fft => write!(f, "fft"),
+            Solver::Tree(theta) => write!(f, "tree({})", theta),
+        }
+    }
+}
+
+/// Error of a solver compared to the direct sum, as returned by
+/// [`BiotSavart::estimate_error`](struct.BiotSavart.html#method.estimate_error).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ErrorEstimate {
+    /// Largest |B - B_direct| of the sampled points
+    pub max_abs: f64,
+    /// `max_abs` relative to the largest |B_direct| of the sampled points
+    pub max_rel: f64,
+    /// Root mean square of |B - B_direct| relative to that of |B_direct|
+    pub rms_rel: f64,
+}
+
+/// Biot-Savart solver for a current density, J, sampled on a rectilinear grid.
+///
+/// The current density components are borrowed, so an `Array3<f64>` can be
+/// passed with `.view()` without copying it. Both C and Fortran ordered
+/// arrays are accepted.
+pub struct BiotSavart<'a> {
+    jx: ArrayView3<'a, f64>,
+    jy: ArrayView3<'a, f64>,
+    jz: ArrayView3<'a, f64>,
+    x_cor: &'a [f64],
+    y_cor: &'a [f64],
+    z_cor: &'a [f64],
+    dx: Vec<f64>,
+    dy: Vec<f64>,
+    dz: Vec<f64>,
+    units: Units,
+    convention: Convention,
+    solver: Solver,
+    singularity: Singularity,
+    backend: Backend,
+    cancel: CancelToken,
+    progress: Option<&'a dyn Progress>,
+}
+
+// Evaluation of ∫ J × r / r³ dV at a target, set up once for all targets
+enum Method {
+    Direct(Kernel),
+    Tree(Tree),
+}
+
+impl<'a> BiotSavart<'a> {
+    /// Creates a solver for the current density `jx`, `jy`, `jz`.
+    ///
+    /// The components have to be of size MxNxK, with `x_cor`, `y_cor` and `z_cor`
+    /// holding the M, N and K coordinates of the grid points. The results are in
+    /// atomic units unless other units are chosen with [`units`](#method.units).
+    ///
+    /// Returns an error if a coordinate array is empty, not strictly monotonic or
+    /// not finite, or if the components of J do not match the grid or are not
+    /// finite.
+    pub fn new(
+        jx: ArrayView3<'a, f64>,
+        jy: ArrayView3<'a, f64>,
+        jz: ArrayView3<'a, f64>,
+        x_cor: &'a [f64],
+        y_cor: &'a [f64],
+        z_cor: &'a [f64],
+    ) -> Result<Self> {
+        check_coordinates("x_cor", x_cor)?;
+        check_coordinates("y_cor", y_cor)?;
+        check_coordinates("z_cor", z_cor)?;
+        let shape = [x_cor.len(), y_cor.len(), z_cor.len()];
+        for (name, j) in [("jx", &jx), ("jy", &jy), ("jz", &jz)].iter() {
+            check_shape(name, j.shape(), &shape)?;
+            check_finite(name, j.iter())?;
+        }
+
+        Ok(BiotSavart {
+            jx,
+            jy,
+            jz,
+            x_cor,
+            y_cor,
+            z_cor,
+            dx: cell_widths(x_cor),
+            dy: cell_widths(y_cor),
+            dz: cell_widths(z_cor),
+            units: Units::default(),
+            convention: Convention::default(),
+            solver: Solver::default(),
+            singularity: Singularity::default(),
+            backend: Backend::default(),
+            cancel: CancelToken::new(),
+            progress: None,
+        })
+    }
+
+    /// Sets the unit system of the input and the results.
+    pub fn units(mut self, units: Units) -> Self {
+        self.units = units;
+        self
+    }
+
+    /// Sets the sign convention of J, the electric current density by default.
+    /// With [`Convention::ElectronFlux`](enum.Convention.html#variant.ElectronFlux)
+    /// J is negated, which flips the sign of every result.
+    pub fn convention(mut self, convention: Convention) -> Self {
+        self.convention = convention;
+        self
+    }
+
+    /// Sets the method used to calculate the field. The FFT solver only
+    /// calculates B on the grid of J, the other targets return
+    /// [`Error::FftOffGrid`](enum.Error.html#variant.FftOffGrid) for it.
+    pub fn solver(mut self, solver: Solver) -> Self {
+        self.solver = solver;
+        self
+    }
+
+    /// Sets the treatment of targets that coincide with grid points of J.
+    pub fn singularity(mut self, singularity: Singularity) -> Self {
+        self.singularity = singularity;
+        self
+    }
+
+    /// Sets the instruction set of the direct sum, which is picked at runtime by
+    /// default. Calculations with a backend the CPU does not support return
+    /// [`Error::UnsupportedBackend`](enum.Error.html#variant.UnsupportedBackend).
+    pub fn backend(mut self, backend: Backend) -> Self {
+        self.backend = backend;
+        self
+    }
+
+    /// Sets a token to stop the calculation of B from another thread. Once it is
+    /// cancelled, the remaining targets are skipped and the calculation returns
+    /// [`Error::Cancelled`](enum.Error.html#variant.Cancelled).
+    pub fn cancel_token(mut self, cancel: CancelToken) -> Self {
+        self.cancel = cancel;
+        self
+    }
+
+    /// Sets a hook that is told how much of the calculation of B is done.
+    pub fn progress(mut self, progress: &'a dyn Progress) -> Self {
+        self.progress = Some(progress);
+        self
+    }
+
+    /// Returns true if the grid of J is uniformly spaced, as required by
+    /// [`Solver::Fft`](enum.Solver.html#variant.Fft).
+    pub fn is_uniform(&self) -> bool {
+        self.spacing().is_some()
+    }
+
+    /// Calculates the magnetic field, B, at every point of the grid.
+    ///
+    /// Returns Bx, By and Bz, each with the same shape as J, or
+    /// [`Error::NonUniform`](enum.Error.html#variant.NonUniform) if the FFT
+    /// solver is chosen for a grid that is not uniformly spaced.
+    pub fn field(&self) -> Result<(Array3<f64>, Array3<f64>, Array3<f64>)> {
+        match self.solver {
+            Solver::Direct | Solver::Tree(_) => {
+                self.field_on_grid(self.x_cor, self.y_cor, self.z_cor)
+            }
+            Solver::Fft => {
+                let h = self.spacing().ok_or(Error::NonUniform)?;
+                info!(
+                    "calculating B on the {}x{}x{} grid of J by FFT",
+                    self.x_cor.len(),
+                    self.y_cor.len(),
+                    self.z_cor.len()
+                );
+                let tally = Tally::new(self.progress, 1, CONVOLUTION_STAGES);
+                let dv = self.dx[0] * self.dy[0] * self.dz[0];
+                let (mut b_x, mut b_y, mut b_z) = convolve(
+                    &self.jx,
+                    &self.jy,
+                    &self.jz,
+                    h,
+                    dv,
+                    self.singularity.eps2(),
+                    &self.cancel,
+                    &tally,
+                )?;
+
+                let prefactor = self.field_prefactor();
+                Zip::indexed(&mut b_x)
+                    .and(&mut b_y)
+                    .and(&mut b_z)
+                    .par_apply(|idx, result_x, result_y, result_z| {
+                        let mut b = [*result_x, *result_y, *result_z];
+                        if self.singularity == Singularity::Analytic {
+                            let voxel = self.voxel_field([idx.0, idx.1, idx.2]);
+                            b = [b[0] + voxel[0], b[1] + voxel[1], b[2] + voxel[2]];
+                        }
+
+                        *result_x = b[0] * prefactor;
+                        *result_y = b[1] * prefactor;
+                        *result_z = b[2] * prefactor;
+                    });
+                Ok((b_x, b_y, b_z))
+            }
+        }
+    }
+
+    /// Calculates the magnetic field, B, on a rectilinear target grid.
+    ///
+    /// The target grid is independent of the grid of J, and can be finer, larger,
+    /// or a plane or line by giving a single coordinate along some dimensions.
+    /// Returns Bx, By and Bz, each of size `x.len()` x `y.len()` x `z.len()`.
+    /// The FFT solver only takes the grid of J itself.
+    pub fn field_on_grid(
+        &self,
+        x: &[f64],
+        y: &[f64],
+        z: &[f64],
+    ) -> Result<(Array3<f64>, Array3<f64>, Array3<f64>)> {
+        check_finite("target_x", x)?;
+        check_finite("target_y", y)?;
+        check_finite("target_z", z)?;
+        if self.solver == Solver::Fft && (x, y, z) == (self.x_cor, self.y_cor, self.z_cor) {
+            return self.field();
+        }
+
+        let shape = (x.len(), y.len(), z.len());
+        let mut b_x = Array3::<f64>::zeros(shape);
+        let mut b_y = Array3::<f64>::zeros(shape);
+        let mut b_z = Array3::<f64>::zeros(shape);
+        let prefactor = self.field_prefactor();
+        let method = self.method()?;
+        info!(
+            "calculating B on a {}x{}x{} grid with the {:?} solver",
+            shape.0, shape.1, shape.2, self.solver
+        );
+        let tally = Tally::new(self.progress, shape.1 * shape.2, shape.0);
+
+        Zip::indexed(&mut b_x)
+            .and(&mut b_y)
+            .and(&mut b_z)
+            .par_apply(|idx, result_x, result_y, result_z| {
+                if self.cancel.is_cancelled() {
+                    return;
+                }
+                let b_r = [x[idx.0], y[idx.1], z[idx.2]];
+                let b = self.integrate_with(&method, &b_r);
+
+                *result_x = b[0] * prefactor;
+                *result_y = b[1] * prefactor;
+                *result_z = b[2] * prefactor;
+                tally.add();
+            });
+        self.check_cancelled()?;
+
+        Ok((b_x, b_y, b_z))
+    }
+
+    /// Calculates the magnetic field, B, at arbitrary points.
+    ///
+    /// `points` is an Nx3 array of x-, y-, z- coordinates. Returns an Nx3 array
+    /// holding Bx, By and Bz at each point. The cost is proportional to the number
+    /// of points times the size of the grid, so probing a few points is cheap.
+    pub fn field_at(&self, points: ArrayView2<f64>) -> Result<Array2<f64>> {
+        check_shape("points", &[points.ncols()], &[3])?;
+        check_finite("points", points.iter())?;
+
+        self.field_at_with(points, &self.method()?)
+    }
+
+    /// Estimates the error of the chosen solver by comparing
+    /// [`field`](#method.field) to the direct sum at `samples` grid points
+    /// spread evenly through the grid. The relative errors are 0 if the solver
+    /// matches the direct sum exactly, also when there are no samples or the
+    /// field vanishes.
+    pub fn estimate_error(&self, samples: usize) -> Result<ErrorEstimate> {
+        let (nx, ny, nz) = self.jx.dim();
+        let total = nx * ny * nz;
+        let samples = samples.min(total);
+        let indices: Vec<(usize, usize, usize)> = (0..samples)
+            .map(|n| n * total / samples)
+            .map(|n| (n / (ny * nz), (n / nz) % ny, n % nz))
+            .collect();
+        let points = Array2::from_shape_fn((samples, 3), |(n, a)| {
+            let idx = indices[n];
+            [self.x_cor[idx.0], self.y_cor[idx.1], self.z_cor[idx.2]][a]
+        });
+
+        let b = match self.solver {
+            Solver::Fft => {
+                let (b_x, b_y, b_z) = self.field()?;
+                Array2::from_shape_fn((samples, 3), |(n, a)| {
+                    let idx = indices[n];
+                    [b_x[idx], b_y[idx], b_z[idx]][a]
+                })
+            }
+            _ => self.field_at(points.view())?,
+        };
+        let direct = self.field_at_with(points.view(), &Method::Direct(self.kernel()?))?;
+
+        let mut max_abs = 0f64;
+        let mut max_direct = 0f64;
+        let mut sum_error = 0f64;
+        let mut sum_direct = 0f64;
+        for (b, direct) in b.genrows().into_iter().zip(direct.genrows()) {
+            let error = (&b - &direct).dot(&(&b - &direct));
+            let norm = direct.dot(&direct);
+            max_abs = max_abs.max(error.sqrt());
+            max_direct = max_direct.max(norm.sqrt());
+            sum_error += error;
+            sum_direct += norm;
+        }
+
+        // without samples, or without a field to compare to, there is no error
+        // unless the solver made one up
+        let relative = |error: f64, direct: f64| if error == 0.0 { 0.0 } else { error / direct };
+        Ok(ErrorEstimate {
+            max_abs,
+            max_rel: relative(max_abs, max_direct),
+            rms_rel: relative(sum_error, sum_direct).sqrt(),
+        })
+    }
+
+    /// Calculates the magnetization of the current density around `center`.
+    ///
+    /// m = k ∫ r × J dV, with r measured from `center`, so m points along z
+    /// for a current circulating counterclockwise around z, like B at its
+    /// center. Versions before 0.2 returned its negative.
+    pub fn magnetization(&self, center: [f64; 3]) -> [f64; 3] {
+        self.magnetization_density(center).0
+    }
+
+    /// Calculates the magnetization around `center` together with the local
+    /// magnetization density, M = k r × J, which it is the integral of.
+    ///
+    /// Returns the magnetization, and Mx, My and Mz at every point of the grid
+    /// of J, in units of the magnetization per volume, e.g. A/m for SI. The
+    /// regions where M is large dominate the magnetization. Like the
+    /// magnetization, M depends on `center`.
+    pub fn magnetization_density(
+        &self,
+        center: [f64; 3],
+    ) -> ([f64; 3], (Array3<f64>, Array3<f64>, Array3<f64>)) {
+        debug!("calculating m around {:?}", center);
+        let (mut m_x, mut m_y, mut m_z) = calculate_magnetization(
+            Array1::from(center.to_vec()),
+            &self.jx,
+            &self.jy,
+            &self.jz,
+            self.x_cor,
+            self.y_cor,
+            self.z_cor,
+        );
+
+        let prefactor = self.units.magnetization_prefactor() * self.convention.sign();
+        for density in &mut [&mut m_x, &mut m_y, &mut m_z] {
+            density.mapv_inplace(|m| m * prefactor);
+        }
+        let m = [
+            self.volume_integral(&m_x),
+            self.volume_integral(&m_y),
+            self.volume_integral(&m_z),
+        ];
+        (m, (m_x, m_y, m_z))
+    }
+
+    /// Calculates the magnetic dipole, quadrupole and toroidal moments of the
+    /// current density around `center`, together with the net current, which
+    /// makes them depend on the center.
+    pub fn multipoles(&self, center: [f64; 3]) -> Multipoles {
+        debug!("calculating the multipoles around {:?}", center);
+        multipoles(
+            [&self.jx, &self.jy, &self.jz],
+            [self.x_cor, self.y_cor, self.z_cor],
+            [&self.dx, &self.dy, &self.dz],
+            center,
+            self.units.magnetization_prefactor() * self.convention.sign(),
+            self.convention.sign(),
+        )
+    }
+
+    /// Integrates the current through a half-plane, as the ring-current
+    /// strength of GIMIC, with J interpolated trilinearly between the grid
+    /// points, and zero outside of the grid.
+    ///
+    /// J has to be the current density induced by an external field of 1 along
+    /// `plane.up`, in the units of the solver. Returns
+    /// [`Error::InvalidPlane`](enum.Error.html#variant.InvalidPlane) if the
+    /// plane is degenerate.
+    pub fn current_strength(&self, plane: &HalfPlane) -> Result<CurrentStrength> {
+        debug!("integrating J over {:?}", plane);
+        current_strength(
+            [&self.jx, &self.jy, &self.jz],
+            [self.x_cor, self.y_cor, self.z_cor],
+            self.units.current_strength_prefactor() * self.convention.sign(),
+            plane,
+        )
+    }
+
+    // Prefactor of the Biot-Savart integral over J as given
+    fn field_prefactor(&self) -> f64 {
+        self.units.field_prefactor() * self.convention.sign()
+    }
+
+    // Integrates values at the grid points of J over the cells around them
+    fn volume_integral(&self, values: &Array3<f64>) -> f64 {
+        values
+            .indexed_iter()
+            .map(|((i, j, k), value)| value * self.dx[i] * self.dy[j] * self.dz[k])
+            .sum()
+    }
+
+    fn spacing(&self) -> Option<[f64; 3]> {
+        Some([
+            uniform_spacing(self.x_cor)?,
+            uniform_spacing(self.y_cor)?,
+            uniform_spacing(self.z_cor)?,
+        ])
+    }
+
+    fn kernel(&self) -> Result<Kernel> {
+        if self.backend.is_supported() {
+            let backend = match self.backend {
+                Backend::Auto => Backend::detect(),
+                backend => backend,
+            };
+            debug!("direct sum with the {} backend", backend);
+            Ok(kernel(backend))
+        } else {
+            Err(Error::UnsupportedBackend(self.backend))
+        }
+    }
+
+    fn check_cancelled(&self) -> Result<()> {
+        if self.cancel.is_cancelled() {
+            Err(Error::Cancelled)
+        } else {
+            Ok(())
+        }
+    }
+
+    // Picks the kernel of the direct sum, or builds the octree of the tree code
+    fn method(&self) -> Result<Method> {
+        self.check_cancelled()?;
+        match self.solver {
+            Solver::Tree(theta) if theta.is_nan() || theta < 0.0 => Err(Error::InvalidTheta(theta)),
+            Solver::Tree(theta) => Ok(Method::Tree(Tree::new(
+                [&self.jx, &self.jy, &self.jz],
+                [self.x_cor, self.y_cor, self.z_cor],
+                [&self.dx, &self.dy, &self.dz],
+                theta,
+                self.singularity.eps2(),
+            ))),
+            Solver::Fft => Err(Error::FftOffGrid),
+            Solver::Direct => Ok(Method::Direct(self.kernel()?)),
+        }
+    }
+
+    fn field_at_with(&self, points: ArrayView2<f64>, method: &Method) -> Result<Array2<f64>> {
+        let mut b = Array2::<f64>::zeros((points.nrows(), 3));
+        let prefactor = self.field_prefactor();
+        info!(
+            "calculating B at {} points with the {:?} solver",
+            points.nrows(),
+            self.solver
+        );
+        let tally = Tally::new(self.progress, 1, points.nrows());
+
+        Zip::from(b.genrows_mut())
+            .and(points.genrows())
+            .par_apply(|mut result, point| {
+                if self.cancel.is_cancelled() {
+                    return;
+                }
+                let b_r = [point[0], point[1], point[2]];
+                let b_val = self.integrate_with(method, &b_r);
+
+                result[0] = b_val[0] * prefactor;
+                result[1] = b_val[1] * prefactor;
+                result[2] = b_val[2] * prefactor;
+                tally.add();
+            });
+        self.check_cancelled()?;
+
+        Ok(b)
+    }
+
+    fn integrate_with(&self, method: &Method, b_r: &[f64; 3]) -> [f64; 3] {
+        let mut result = match method {
+            Method::Direct(sum) => self.integrate(*sum, b_r),
+            Method::Tree(tree) => tree.field_at(b_r),
+        };
+
+        if self.singularity == Singularity::Analytic {
+            let idx = (
+                grid_index(self.x_cor, &self.dx, b_r[0]),
+                grid_index(self.y_cor, &self.dy, b_r[1]),
+                grid_index(self.z_cor, &self.dz, b_r[2]),
+            );
+            if let (Some(xi), Some(yi), Some(zi)) = idx {
+                let voxel = self.voxel_field([xi, yi, zi]);
+                result = [
+                    result[0] + voxel[0],
+                    result[1] + voxel[1],
+                    result[2] + voxel[2],
+                ];
+            }
+        }
+
+        result
+    }
+
+    // Integral over the voxel around a grid point that is left out of the sum
+    fn voxel_field(&self, idx: [usize; 3]) -> [f64; 3] {
+        voxel_field(
+            [&self.jx, &self.jy, &self.jz],
+            [self.x_cor, self.y_cor, self.z_cor],
+            [&self.dx, &self.dy, &self.dz],
+            idx,
+        )
+    }
+
+    // Returns ∫ J × r / r³ dV at `b_r`, the caller applies the unit dependent
+    // prefactor.
+    fn integrate(&self, sum: Kernel, b_r: &[f64; 3]) -> [f64; 3] {
+        let mut result = [0f64; 3];
+        let mut buf_x = [0f64; CHUNK];
+        let mut buf_y = [0f64; CHUNK];
+        let mut buf_z = [0f64; CHUNK];
+        let eps2 = self.singularity.eps2();
+
+        let nz = self.z_cor.len();
+
+        for (xi, x) in self.x_cor.iter().enumerate() {
+            for (yi, y) in self.y_cor.iter().enumerate() {
+                let w = self.dx[xi] * self.dy[yi];
+                for start in (0..nz).step_by(CHUNK) {
+                    let len = CHUNK.min(nz - start);
+                    let z = &self.z_cor[start..start + len];
+                    let dz = &self.dz[start..start + len];
+
+                    let jx = chunk(&self.jx, xi, yi, start, len, &mut buf_x);
+                    let jy = chunk(&self.jy, xi, yi, start, len, &mut buf_y);
+                    let jz = chunk(&self.jz, xi, yi, start, len, &mut buf_z);
+
+                    // the kernel is only picked for backends the CPU supports
+                    let b = unsafe { sum(b_r, *x, *y, z, w, dz, eps2, jx, jy, jz) };
+                    result[0] += b[0];
+                    result[1] += b[1];
+                    result[2] += b[2];
+                }
+            }
+        }
+
+        result
+    }
+}
+
+// Calculates the magnetization in accordance to the paper
+// "Local Current Density Calculations for Molecular Films from Ab Initio"
+// DOI: 10.1021/acs.jctc.5b00471
+// J. Chem. Theory Comput. 2015, 11, 5161−5176
+//
+// Returns r × J at every grid point, which the caller integrates after applying
+// the unit dependent prefactor and the sign of the convention of J, as the paper
+// uses the flux of the electrons.
+fn calculate_magnetization(
+    center: Array1<f64>,
+    jx: &ArrayView3<f64>,
+    jy: &ArrayView3<f64>,
+    jz: &ArrayView3<f64>,
+    x_cor: &[f64],
+    y_cor: &[f64],
+    z_cor: &[f64],
+) -> (Array3<f64>, Array3<f64>, Array3<f64>) {
+    let mut temp_x = Array3::<f64>::zeros(jx.dim());
+    let mut temp_y = Array3::<f64>::zeros(jy.dim());
+    let mut temp_z = Array3::<f64>::zeros(jz.dim());
+
+    Zip::indexed(&mut temp_x)
+        .and(&mut temp_y)
+        .and(&mut temp_z)
+        .par_apply(|idx, result_x, result_y, result_z| {
+            let b_r = array![
+                x_cor[idx.0] as f64,
+                y_cor[idx.1] as f64,
+                z_cor[idx.2] as f64
+            ] - &center;
+            let jx_val = &jx[[idx.0, idx.1, idx.2]];
+            let jy_val = &jy[[idx.0, idx.1, idx.2]];
+            let jz_val = &jz[[idx.0, idx.1, idx.2]];
+
+            *result_x = b_r[1] * jz_val - jy_val * b_r[2];
+            *result_y = -b_r[0] * jz_val + jx_val * b_r[2];
+            *result_z = b_r[0] * jy_val - jx_val * b_r[1];
+        });
+
+    (temp_x, temp_y, temp_z)
+}
+
+// Returns `len` values of J along the third dimension, starting at `start`.
+// Lanes that are not contiguous in memory (Fortran ordered input) are copied
+// into `buf`, so the input itself never has to be copied.
+fn chunk<'b>(
+    j: &'b ArrayView3<f64>,
+    xi: usize,
+    yi: usize,
+    start: usize,
+    len: usize,
+    buf: &'b mut [f64; CHUNK],
+) -> &'b [f64] {
+    let lane = j.slice(s![xi, yi, start..start + len]);
+    match lane.to_slice() {
+        Some(values) => values,
+        None => {
+            for (b, val) in buf.iter_mut().zip(lane.iter()) {
+                *b = *val;
+            }
+            &buf[..len]
+        }
+    }
+}
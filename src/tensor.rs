@@ -0,0 +1,67 @@
+// Largest number of Jacobi sweeps, far more than a 3x3 matrix needs
+const MAX_SWEEPS: usize = 50;
+
+// Returns the eigenvalues of a symmetric 3x3 matrix in ascending order, and the
+// corresponding unit eigenvectors as rows. Only the upper triangle is used.
+//
+// Uses cyclic Jacobi rotations, which are accurate for the small matrices of
+// response tensors.
+pub(crate) fn symmetric_eigen(matrix: [[f64; 3]; 3]) -> ([f64; 3], [[f64; 3]; 3]) {
+    let mut a = matrix;
+    a[1][0] = a[0][1];
+    a[2][0] = a[0][2];
+    a[2][1] = a[1][2];
+    let mut v = [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]];
+
+    for _ in 0..MAX_SWEEPS {
+        let off = a[0][1] * a[0][1] + a[0][2] * a[0][2] + a[1][2] * a[1][2];
+        let diagonal = a[0][0] * a[0][0] + a[1][1] * a[1][1] + a[2][2] * a[2][2];
+        if off <= f64::EPSILON * f64::EPSILON * diagonal || off == 0.0 {
+            break;
+        }
+        for &(p, q) in &[(0, 1), (0, 2), (1, 2)] {
+            if a[p][q] == 0.0 {
+                continue;
+            }
+            // rotation by the angle that zeroes a[p][q]
+            let theta = (a[q][q] - a[p][p]) / (2.0 * a[p][q]);
+            let t = theta.signum() / (theta.abs() + (theta * theta + 1.0).sqrt());
+            let c = 1.0 / (t * t + 1.0).sqrt();
+            let s = t * c;
+            let rotate = |x: f64, y: f64| (c * x - s * y, s * x + c * y);
+            // A becomes Rᵀ A R, and V becomes V R
+            for row in a.iter_mut().chain(v.iter_mut()) {
+                let (x, y) = rotate(row[p], row[q]);
+                row[p] = x;
+                row[q] = y;
+            }
+            let (row_p, row_q) = (a[p], a[q]);
+            for (k, (&x, &y)) in row_p.iter().zip(&row_q).enumerate() {
+                let (x, y) = rotate(x, y);
+                a[p][k] = x;
+                a[q][k] = y;
+            }
+        }
+    }
+
+    let mut order = [0, 1, 2];
+    order.sort_by(|&i, &j| a[i][i].partial_cmp(&a[j][j]).unwrap());
+    let values = [
+        a[order[0]][order[0]],
+        a[order[1]][order[1]],
+        a[order[2]][order[2]],
+    ];
+    let vector = |i: usize| [v[0][order[i]], v[1][order[i]], v[2][order[i]]];
+    (values, [vector(0), vector(1), vector(2)])
+}
+
+// Returns the symmetric part, (A + Aᵀ) / 2.
+pub(crate) fn symmetric_part(a: [[f64; 3]; 3]) -> [[f64; 3]; 3] {
+    let mut s = [[0.0; 3]; 3];
+    for (i, row) in s.iter_mut().enumerate() {
+        for (j, value) in row.iter_mut().enumerate() {
+            *value = 0.5 * (a[i][j] + a[j][i]);
+        }
+    }
+    s
+}
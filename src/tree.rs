@@ -0,0 +1,233 @@
+use ndarray::prelude::*;
+
+// Maximum number of sources in a leaf of the octree
+const LEAF_SIZE: usize = 16;
+
+// A grid point with its current element, I = J dV
+#[derive(Clone, Copy)]
+struct Source {
+    r: [f64; 3],
+    i: [f64; 3],
+}
+
+struct Node {
+    // Mean position of the sources, the expansion point of the multipoles
+    center: [f64; 3],
+    // Largest distance from the center to a source
+    radius: f64,
+    // Total current element, Σ I
+    current: [f64; 3],
+    // Dipole moment of the current elements, Σ I_a (r - center)_b
+    moment: [[f64; 3]; 3],
+    // Range of the sources below this node
+    start: usize,
+    end: usize,
+    children: Vec<usize>,
+}
+
+// Barnes-Hut octree over the current elements of a grid.
+//
+// Cells that are far from the target, radius < theta * distance, are replaced
+// by the expansion of J × r / r³ around their center up to first order in the
+// source positions. theta = 0 reduces to the direct sum. The softening length
+// only enters the exact sums, as it is negligible at the distance of expanded
+// cells.
+pub(crate) struct Tree {
+    sources: Vec<Source>,
+    nodes: Vec<Node>,
+    theta: f64,
+    eps2: f64,
+}
+
+impl Tree {
+    pub(crate) fn new(
+        j: [&ArrayView3<f64>; 3],
+        cor: [&[f64]; 3],
+        widths: [&[f64]; 3],
+        theta: f64,
+        eps2: f64,
+    ) -> Self {
+        let mut sources = Vec::new();
+        for ((idx, jx), (jy, jz)) in j[0].indexed_iter().zip(j[1].iter().zip(j[2].iter())) {
+            // points without current do not contribute
+            if *jx == 0.0 && *jy == 0.0 && *jz == 0.0 {
+                continue;
+            }
+            let dv = widths[0][idx.0] * widths[1][idx.1] * widths[2][idx.2];
+            sources.push(Source {
+                r: [cor[0][idx.0], cor[1][idx.1], cor[2][idx.2]],
+                i: [jx * dv, jy * dv, jz * dv],
+            });
+        }
+
+        let mut tree = Tree {
+            sources,
+            nodes: Vec::new(),
+            theta,
+            eps2,
+        };
+        if !tree.sources.is_empty() {
+            let len = tree.sources.len();
+            tree.build(0, len);
+        }
+        tree
+    }
+
+    // Builds the node for sources[start..end] and its children, and returns
+    // its index.
+    fn build(&mut self, start: usize, end: usize) -> usize {
+        let sources = &self.sources[start..end];
+        let n = sources.len() as f64;
+
+        let mut center = [0f64; 3];
+        let mut current = [0f64; 3];
+        for source in sources {
+            for a in 0..3 {
+                center[a] += source.r[a] / n;
+                current[a] += source.i[a];
+            }
+        }
+
+        let mut moment = [[0f64; 3]; 3];
+        let mut radius = 0f64;
+        let mut lower = [std::f64::INFINITY; 3];
+        let mut upper = [std::f64::NEG_INFINITY; 3];
+        for source in sources {
+            let delta = sub(&source.r, &center);
+            radius = radius.max(norm(&delta));
+            for a in 0..3 {
+                for b in 0..3 {
+                    moment[a][b] += source.i[a] * delta[b];
+                }
+                lower[a] = lower[a].min(source.r[a]);
+                upper[a] = upper[a].max(source.r[a]);
+            }
+        }
+
+        let index = self.nodes.len();
+        self.nodes.push(Node {
+            center,
+            radius,
+            current,
+            moment,
+            start,
+            end,
+            children: Vec::new(),
+        });
+
+        if end - start <= LEAF_SIZE || radius == 0.0 {
+            return index;
+        }
+
+        // Split the sources into the octants around the middle of their bounding box
+        let middle = [
+            0.5 * (lower[0] + upper[0]),
+            0.5 * (lower[1] + upper[1]),
+            0.5 * (lower[2] + upper[2]),
+        ];
+        let octant = |source: &Source| {
+            (0..3).fold(0, |octant, a| {
+                octant | (((source.r[a] > middle[a]) as usize) << a)
+            })
+        };
+        self.sources[start..end].sort_by_key(octant);
+
+        let mut children = Vec::new();
+        let mut child_start = start;
+        while child_start < end {
+            let key = octant(&self.sources[child_start]);
+            let child_end = child_start
+                + self.sources[child_start..end]
+                    .iter()
+                    .take_while(|source| octant(source) == key)
+                    .count();
+            children.push(self.build(child_start, child_end));
+            child_start = child_end;
+        }
+        self.nodes[index].children = children;
+
+        index
+    }
+
+    // Returns ∫ J × r / r³ dV at `b_r`
+    pub(crate) fn field_at(&self, b_r: &[f64; 3]) -> [f64; 3] {
+        let mut result = [0f64; 3];
+        if self.nodes.is_empty() {
+            return result;
+        }
+
+        let mut stack = vec![0];
+        while let Some(index) = stack.pop() {
+            let node = &self.nodes[index];
+            let d = sub(b_r, &node.center);
+            let distance = norm(&d);
+
+            if node.radius < self.theta * distance {
+                add(&mut result, &expansion(node, &d, distance));
+            } else if node.children.is_empty() {
+                for source in &self.sources[node.start..node.end] {
+                    let r = sub(b_r, &source.r);
+                    let r2 = r[0] * r[0] + r[1] * r[1] + r[2] * r[2] + self.eps2;
+                    if r2 > 0.0 {
+                        let r3 = r2 * r2.sqrt();
+                        let b = cross(&source.i, &r);
+                        add(&mut result, &[b[0] / r3, b[1] / r3, b[2] / r3]);
+                    }
+                }
+            } else {
+                stack.extend_from_slice(&node.children);
+            }
+        }
+
+        result
+    }
+}
+
+// Field of a node at d = r - center, to first order in the source positions:
+// I × K(d) - Σ I × (δ · ∇) K(d), with K(d) = d / |d|³ and δ = r_s - center.
+fn expansion(node: &Node, d: &[f64; 3], distance: f64) -> [f64; 3] {
+    let r3 = distance * distance * distance;
+    let r5 = r3 * distance * distance;
+    let k = [d[0] / r3, d[1] / r3, d[2] / r3];
+
+    // p[a][b] = Σ I_a (G δ)_b with the gradient G_bj = δ_bj / |d|³ - 3 d_b d_j / |d|⁵
+    let mut p = [[0f64; 3]; 3];
+    for (a, p_a) in p.iter_mut().enumerate() {
+        for (b, p_ab) in p_a.iter_mut().enumerate() {
+            for j in 0..3 {
+                let kronecker = if b == j { 1.0 / r3 } else { 0.0 };
+                let g = kronecker - 3.0 * d[b] * d[j] / r5;
+                *p_ab += node.moment[a][j] * g;
+            }
+        }
+    }
+
+    let b = cross(&node.current, &k);
+    [
+        b[0] - (p[1][2] - p[2][1]),
+        b[1] - (p[2][0] - p[0][2]),
+        b[2] - (p[0][1] - p[1][0]),
+    ]
+}
+
+fn sub(a: &[f64; 3], b: &[f64; 3]) -> [f64; 3] {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+fn add(a: &mut [f64; 3], b: &[f64; 3]) {
+    a[0] += b[0];
+    a[1] += b[1];
+    a[2] += b[2];
+}
+
+fn norm(a: &[f64; 3]) -> f64 {
+    (a[0] * a[0] + a[1] * a[1] + a[2] * a[2]).sqrt()
+}
+
+fn cross(a: &[f64; 3], b: &[f64; 3]) -> [f64; 3] {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
@@ -0,0 +1,90 @@
+use std::fmt;
+use std::str::FromStr;
+
+// Speed of light in cm/s
+const SPEED_OF_LIGHT_CGS: f64 = 2.997_924_58e10;
+// Fine-structure constant, CODATA 2018
+const FINE_STRUCTURE: f64 = 7.297_352_569_3e-3;
+// Bohr radius in Ångström, CODATA 2018
+pub(crate) const BOHR_IN_ANGSTROM: f64 = 0.529_177_210_903;
+// Atomic unit of current per atomic unit of magnetic field, e² E_h a0² / ħ², in
+// nA/T, CODATA 2018
+const ATOMIC_CURRENT_PER_FIELD: f64 = 28.179_403_281;
+
+/// Unit system of the current density, the grid coordinates and the results.
+///
+/// | Units      | coordinates | J          | B      | m             |
+/// |------------|-------------|------------|--------|---------------|
+/// | `SI`       | m           | A/m²       | T      | A m²          |
+/// | `Gaussian` | cm          | statA/cm²  | G      | erg/G         |
+/// | `Atomic`   | bohr        | a.u.       | a.u.   | a.u.          |
+///
+/// `Atomic` refers to SI-based Hartree atomic units, where μ0/4π = α².
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Units {
+    SI,
+    Gaussian,
+    Atomic,
+}
+
+impl Units {
+    /// Prefactor of the Biot-Savart integral, B = k ∫ J × r / r³ dV.
+    pub fn field_prefactor(self) -> f64 {
+        match self {
+            Units::SI => 1e-7,
+            Units::Gaussian => 1.0 / SPEED_OF_LIGHT_CGS,
+            Units::Atomic => FINE_STRUCTURE * FINE_STRUCTURE,
+        }
+    }
+
+    /// Prefactor of the magnetization integral, m = k ∫ r × J dV.
+    pub fn magnetization_prefactor(self) -> f64 {
+        match self {
+            Units::SI | Units::Atomic => 0.5,
+            Units::Gaussian => 0.5 / SPEED_OF_LIGHT_CGS,
+        }
+    }
+
+    /// Factor converting the current, ∫ J · n dA, of a current density induced
+    /// by an external field of 1 to nA/T.
+    pub fn current_strength_prefactor(self) -> f64 {
+        match self {
+            Units::SI => 1e9,
+            // 1 statA = 10 / c A, and 1 G = 1e-4 T
+            Units::Gaussian => 1e14 / SPEED_OF_LIGHT_CGS,
+            Units::Atomic => ATOMIC_CURRENT_PER_FIELD,
+        }
+    }
+}
+
+impl Default for Units {
+    fn default() -> Self {
+        Units::Atomic
+    }
+}
+
+impl FromStr for Units {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "si" => Ok(Units::SI),
+            "gaussian" | "cgs" => Ok(Units::Gaussian),
+            "au" | "atomic" => Ok(Units::Atomic),
+            _ => Err(format!(
+                "unknown units '{}', expected 'si', 'gaussian' or 'au'",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Units {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Units::SI => write!(f, "si"),
+            Units::Gaussian => write!(f, "gaussian"),
+            Units::Atomic => write!(f, "au"),
+        }
+    }
+}
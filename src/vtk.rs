@@ -0,0 +1,123 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use crate::error::{Error, Result};
+use crate::fft::uniform_spacing;
+use crate::field::VectorField;
+
+// Type of VTK XML dataset a field is written as
+pub(crate) enum Grid {
+    // RectilinearGrid (.vtr), which lists the coordinates along each dimension
+    Rectilinear,
+    // ImageData (.vti), with an origin and a spacing
+    Image,
+}
+
+// Writes `field` as a VTK XML file in ASCII format.
+pub(crate) fn write(path: &Path, field: &VectorField, name: &str, grid: Grid) -> Result<()> {
+    let cor = [field.x_cor, field.y_cor, field.z_cor];
+    let spacing = match grid {
+        Grid::Rectilinear => None,
+        Grid::Image => {
+            let spacing = |cor: &[f64]| uniform_spacing(cor).ok_or(Error::NonUniform);
+            Some([spacing(cor[0])?, spacing(cor[1])?, spacing(cor[2])?])
+        }
+    };
+
+    File::create(path)
+        .and_then(|file| write_to(BufWriter::new(file), field, name, spacing))
+        .map_err(|err| Error::io(path, err))
+}
+
+fn write_to<W: Write>(
+    mut w: W,
+    field: &VectorField,
+    name: &str,
+    spacing: Option<[f64; 3]>,
+) -> io::Result<()> {
+    let cor = [field.x_cor, field.y_cor, field.z_cor];
+    let (nx, ny, nz) = field.x.dim();
+    let extent = format!("0 {} 0 {} 0 {}", nx - 1, ny - 1, nz - 1);
+    let dataset = match spacing {
+        None => "RectilinearGrid",
+        Some(_) => "ImageData",
+    };
+
+    writeln!(w, "<?xml version=\"1.0\"?>")?;
+    writeln!(
+        w,
+        "<VTKFile type=\"{}\" version=\"0.1\" byte_order=\"LittleEndian\">",
+        dataset
+    )?;
+    match spacing {
+        None => writeln!(w, "  <RectilinearGrid WholeExtent=\"{}\">", extent)?,
+        Some(h) => writeln!(
+            w,
+            "  <ImageData WholeExtent=\"{}\" Origin=\"{:e} {:e} {:e}\" Spacing=\"{:e} {:e} {:e}\">",
+            extent, cor[0][0], cor[1][0], cor[2][0], h[0], h[1], h[2]
+        )?,
+    }
+    writeln!(w, "    <Piece Extent=\"{}\">", extent)?;
+
+    // VTK orders the points with x running fastest
+    writeln!(
+        w,
+        "      <PointData Vectors=\"{}\" Scalars=\"{}_norm\">",
+        name, name
+    )?;
+    writeln!(
+        w,
+        "        <DataArray type=\"Float64\" Name=\"{}\" NumberOfComponents=\"3\" format=\"ascii\">",
+        name
+    )?;
+    for k in 0..nz {
+        for j in 0..ny {
+            for i in 0..nx {
+                let idx = [i, j, k];
+                writeln!(
+                    w,
+                    "          {:e} {:e} {:e}",
+                    field.x[idx], field.y[idx], field.z[idx]
+                )?;
+            }
+        }
+    }
+    writeln!(w, "        </DataArray>")?;
+    writeln!(
+        w,
+        "        <DataArray type=\"Float64\" Name=\"{}_norm\" format=\"ascii\">",
+        name
+    )?;
+    let norm = field.norm();
+    for k in 0..nz {
+        for j in 0..ny {
+            for i in 0..nx {
+                writeln!(w, "          {:e}", norm[[i, j, k]])?;
+            }
+        }
+    }
+    writeln!(w, "        </DataArray>")?;
+    writeln!(w, "      </PointData>")?;
+
+    if spacing.is_none() {
+        writeln!(w, "      <Coordinates>")?;
+        for (axis, cor) in ["x", "y", "z"].iter().zip(&cor) {
+            writeln!(
+                w,
+                "        <DataArray type=\"Float64\" Name=\"{}\" format=\"ascii\">",
+                axis
+            )?;
+            for value in cor.iter() {
+                writeln!(w, "          {:e}", value)?;
+            }
+            writeln!(w, "        </DataArray>")?;
+        }
+        writeln!(w, "      </Coordinates>")?;
+    }
+
+    writeln!(w, "    </Piece>")?;
+    writeln!(w, "  </{}>", dataset)?;
+    writeln!(w, "</VTKFile>")?;
+    w.flush()
+}
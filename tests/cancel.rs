@@ -0,0 +1,66 @@
+extern crate biot_savart;
+extern crate ndarray;
+
+mod common;
+
+use std::sync::Mutex;
+
+use biot_savart::{BiotSavart, CancelToken, Error, Solver};
+use ndarray::prelude::*;
+
+use common::current_density;
+
+#[test]
+fn cancelled_token_stops_every_solver() {
+    let x: Vec<f64> = (0..6).map(|i| i as f64 * 0.5).collect();
+    let (jx, jy, jz) = current_density((x.len(), x.len(), x.len()));
+    let cancel = CancelToken::new();
+    cancel.cancel();
+
+    for solver in [Solver::Direct, Solver::Fft, Solver::Tree(0.5)].iter() {
+        let biot = BiotSavart::new(jx.view(), jy.view(), jz.view(), &x, &x, &x)
+            .unwrap()
+            .solver(*solver)
+            .cancel_token(cancel.clone());
+        assert_eq!(biot.field().unwrap_err(), Error::Cancelled);
+        assert_eq!(
+            biot.field_at(array![[0.1, 0.2, 0.3]].view()).unwrap_err(),
+            Error::Cancelled
+        );
+    }
+}
+
+#[test]
+fn uncancelled_token_does_not_change_result() {
+    let x: Vec<f64> = (0..5).map(|i| i as f64 * 0.5).collect();
+    let (jx, jy, jz) = current_density((x.len(), x.len(), x.len()));
+
+    let biot = BiotSavart::new(jx.view(), jy.view(), jz.view(), &x, &x, &x).unwrap();
+    let expected = biot.field().unwrap();
+    let found = biot.cancel_token(CancelToken::new()).field().unwrap();
+    assert_eq!(expected, found);
+}
+
+#[test]
+fn fft_stops_between_stages() {
+    let x: Vec<f64> = (0..6).map(|i| i as f64 * 0.5).collect();
+    let (jx, jy, jz) = current_density((x.len(), x.len(), x.len()));
+    let cancel = CancelToken::new();
+
+    // cancelled once the first stage is done
+    let reports = Mutex::new(Vec::new());
+    let progress = |done: usize, _: usize| {
+        reports.lock().unwrap().push(done);
+        if done == 1 {
+            cancel.cancel();
+        }
+    };
+    let result = BiotSavart::new(jx.view(), jy.view(), jz.view(), &x, &x, &x)
+        .unwrap()
+        .solver(Solver::Fft)
+        .cancel_token(cancel.clone())
+        .progress(&progress)
+        .field();
+    assert_eq!(result.unwrap_err(), Error::Cancelled);
+    assert_eq!(reports.into_inner().unwrap(), vec![0, 1]);
+}
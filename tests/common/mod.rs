@@ -0,0 +1,79 @@
+// Helpers shared by the integration tests. Each test crate uses only some of
+// them.
+#![allow(dead_code)]
+
+use std::env;
+use std::path::PathBuf;
+use std::process;
+
+use ndarray::prelude::*;
+
+// Arbitrary current density of small integers on a grid of `shape`.
+pub fn current_density(shape: (usize, usize, usize)) -> (Array3<f64>, Array3<f64>, Array3<f64>) {
+    let jx = Array3::from_shape_fn(shape, |(i, j, k)| {
+        ((3 * i + 5 * j + 7 * k) % 11) as f64 - 5.0
+    });
+    let jy = Array3::from_shape_fn(shape, |(i, j, k)| {
+        ((7 * i + 2 * j + 3 * k) % 13) as f64 - 6.0
+    });
+    let jz = Array3::from_shape_fn(shape, |(i, j, k)| ((5 * i + 11 * j + k) % 7) as f64 - 3.0);
+    (jx, jy, jz)
+}
+
+// Returns a path in the temporary directory that is unique to the test process.
+pub fn temp_path(name: &str) -> PathBuf {
+    env::temp_dir().join(format!("biot_savart_{}_{}", process::id(), name))
+}
+
+// Compares with a tolerance relative to the larger value, or absolute below 1.
+pub fn close(a: f64, b: f64) -> bool {
+    (a - b).abs() <= 1e-10 * a.abs().max(b.abs()).max(1.0)
+}
+
+// ∫ J × r / r³ dV at `target`, summed point by point over the grid of J and
+// leaving out a grid point at the target. The caller applies the prefactor of
+// the units.
+pub fn direct_field(j: [&Array3<f64>; 3], cor: [&[f64]; 3], target: [f64; 3]) -> [f64; 3] {
+    let widths = [
+        cell_widths(cor[0]),
+        cell_widths(cor[1]),
+        cell_widths(cor[2]),
+    ];
+    let mut result = [0.0; 3];
+    for ((i, k, l), &jx) in j[0].indexed_iter() {
+        let current = [jx, j[1][[i, k, l]], j[2][[i, k, l]]];
+        let r = [
+            target[0] - cor[0][i],
+            target[1] - cor[1][k],
+            target[2] - cor[2][l],
+        ];
+        let r2 = r[0] * r[0] + r[1] * r[1] + r[2] * r[2];
+        if r2 == 0.0 {
+            continue;
+        }
+        let scale = widths[0][i] * widths[1][k] * widths[2][l] / (r2 * r2.sqrt());
+        result[0] += (current[1] * r[2] - current[2] * r[1]) * scale;
+        result[1] += (current[2] * r[0] - current[0] * r[2]) * scale;
+        result[2] += (current[0] * r[1] - current[1] * r[0]) * scale;
+    }
+    result
+}
+
+// Widths of the cells around the grid points, which reach halfway to the
+// neighbouring points, with the outermost ones as wide as the gap next to them
+fn cell_widths(cor: &[f64]) -> Vec<f64> {
+    let n = cor.len();
+    if n == 1 {
+        return vec![1.0];
+    }
+    (0..n)
+        .map(|i| {
+            let gap = (cor[(i + 1).min(n - 1)] - cor[i.saturating_sub(1)]).abs();
+            if i == 0 || i == n - 1 {
+                gap
+            } else {
+                gap / 2.0
+            }
+        })
+        .collect()
+}
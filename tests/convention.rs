@@ -0,0 +1,53 @@
+extern crate biot_savart;
+extern crate ndarray;
+
+use biot_savart::{BiotSavart, Convention};
+use ndarray::prelude::*;
+
+// Current circulating counterclockwise around z seen from +z, whose B at the
+// center and magnetic moment point along +z
+fn loop_current(cor: &[f64]) -> [Array3<f64>; 3] {
+    let n = cor.len();
+    let weight = |x: f64, y: f64, z: f64| (-(x * x + y * y + z * z)).exp();
+    [
+        Array3::from_shape_fn((n, n, n), |(i, j, k)| {
+            -cor[j] * weight(cor[i], cor[j], cor[k])
+        }),
+        Array3::from_shape_fn((n, n, n), |(i, j, k)| {
+            cor[i] * weight(cor[i], cor[j], cor[k])
+        }),
+        Array3::zeros((n, n, n)),
+    ]
+}
+
+#[test]
+fn field_and_magnetization_follow_the_current() {
+    let cor: Vec<f64> = (0..12).map(|i| -2.75 + i as f64 * 0.5).collect();
+    let [jx, jy, jz] = loop_current(&cor);
+    let center = arr2(&[[0.0, 0.0, 0.0]]);
+
+    let solver = BiotSavart::new(jx.view(), jy.view(), jz.view(), &cor, &cor, &cor).unwrap();
+    let b = solver.field_at(center.view()).unwrap();
+    let m = solver.magnetization([0.0; 3]);
+    assert!(b[[0, 2]] > 0.0);
+    assert!(m[2] > 0.0);
+
+    let flux = BiotSavart::new(jx.view(), jy.view(), jz.view(), &cor, &cor, &cor)
+        .unwrap()
+        .convention(Convention::ElectronFlux);
+    assert_eq!(flux.field_at(center.view()).unwrap(), -&b);
+    let m_flux = flux.magnetization([0.0; 3]);
+    for (m_flux, m) in m_flux.iter().zip(&m) {
+        assert_eq!(*m_flux, -m);
+    }
+}
+
+#[test]
+fn parses_and_displays_conventions() {
+    for convention in &[Convention::Current, Convention::ElectronFlux] {
+        assert_eq!(convention.to_string().parse(), Ok(*convention));
+    }
+    assert_eq!("electron".parse(), Ok(Convention::ElectronFlux));
+    assert_eq!(Convention::default(), Convention::Current);
+    assert!("holes".parse::<Convention>().is_err());
+}
@@ -0,0 +1,162 @@
+extern crate biot_savart;
+extern crate ndarray;
+
+mod common;
+
+use std::fs;
+use std::path::PathBuf;
+
+use biot_savart::{BiotSavart, Cube, CurrentDensity, Error};
+use ndarray::prelude::*;
+
+use common::temp_path;
+
+// Writes `contents` to a file in the temporary directory and returns its path.
+fn temp_file(name: &str, contents: &str) -> PathBuf {
+    let path = temp_path(&format!("{}.cube", name));
+    fs::write(&path, contents).unwrap();
+    path
+}
+
+fn cube_file(counts: [i32; 3], values: &[f64]) -> String {
+    let mut contents = String::from("current density\nJx\n");
+    contents += "    2   -1.000000   -0.500000    0.000000\n";
+    contents += &format!("{:5}    0.500000    0.000000    0.000000\n", counts[0]);
+    contents += &format!("{:5}    0.000000    0.250000    0.000000\n", counts[1]);
+    contents += &format!("{:5}    0.000000    0.000000    0.200000\n", counts[2]);
+    contents += "    6    6.000000    0.000000    0.000000    0.000000\n";
+    contents += "    1    1.000000    1.000000    0.000000    0.000000\n";
+    for line in values.chunks(6) {
+        for value in line {
+            contents += &format!(" {:13.5E}", value);
+        }
+        contents += "\n";
+    }
+    contents
+}
+
+fn values(n: usize) -> Vec<f64> {
+    (0..n).map(|i| (i as f64 * 0.37).sin()).collect()
+}
+
+#[test]
+fn reads_grid_atoms_and_data() {
+    let data = values(3 * 4 * 5);
+    let path = temp_file("grid", &cube_file([3, 4, 5], &data));
+    let cube = Cube::read(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+
+    assert_eq!(
+        cube.comments,
+        ["current density".to_string(), "Jx".to_string()]
+    );
+    assert_eq!(cube.origin, [-1.0, -0.5, 0.0]);
+    assert_eq!(
+        cube.axes,
+        [[0.5, 0.0, 0.0], [0.0, 0.25, 0.0], [0.0, 0.0, 0.2]]
+    );
+    assert_eq!(cube.atoms.len(), 2);
+    assert_eq!(cube.atoms[1].number, 1);
+    assert_eq!(cube.atoms[1].position, [1.0, 0.0, 0.0]);
+    assert_eq!(cube.data.dim(), (3, 4, 5));
+    // z runs fastest in the file
+    assert!((cube.data[[1, 2, 3]] - data[20 + 2 * 5 + 3]).abs() < 1e-5);
+
+    let [x, y, z] = cube.coordinates().unwrap();
+    assert_eq!(x, vec![-1.0, -0.5, 0.0]);
+    assert_eq!(y, vec![-0.5, -0.25, 0.0, 0.25]);
+    assert_eq!(z.len(), 5);
+}
+
+#[test]
+fn converts_angstrom_to_bohr() {
+    let path = temp_file("angstrom", &cube_file([-2, -2, -2], &values(8)));
+    let cube = Cube::read(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+
+    let bohr = 1.0 / 0.529_177_210_903;
+    assert!((cube.origin[0] + bohr).abs() < 1e-12);
+    assert!((cube.axes[1][1] - 0.25 * bohr).abs() < 1e-12);
+    assert!((cube.atoms[1].position[0] - bohr).abs() < 1e-12);
+}
+
+#[test]
+fn rejects_truncated_data() {
+    let path = temp_file("truncated", &cube_file([3, 4, 5], &values(59)));
+    let err = Cube::read(&path).unwrap_err();
+    fs::remove_file(&path).unwrap();
+
+    match err {
+        Error::InvalidFile { message, .. } => assert!(message.contains("holds 59 values")),
+        err => panic!("unexpected error {:?}", err),
+    }
+}
+
+#[test]
+fn missing_file_is_io_error() {
+    let path = temp_path("missing.cube");
+    match Cube::read(&path).unwrap_err() {
+        Error::Io(_) => {}
+        err => panic!("unexpected error {:?}", err),
+    }
+}
+
+#[test]
+fn current_density_from_cubes_matches_arrays() {
+    let n = 3 * 4 * 5;
+    let data = [
+        values(n),
+        values(2 * n)[n..].to_vec(),
+        values(3 * n)[2 * n..].to_vec(),
+    ];
+    let paths: Vec<PathBuf> = ["jx", "jy", "jz"]
+        .iter()
+        .zip(&data)
+        .map(|(name, data)| temp_file(name, &cube_file([3, 4, 5], data)))
+        .collect();
+    let density = CurrentDensity::from_cubes(&paths[0], &paths[1], &paths[2]).unwrap();
+    let cubes: Vec<Cube> = paths.iter().map(|path| Cube::read(path).unwrap()).collect();
+    for path in &paths {
+        fs::remove_file(path).unwrap();
+    }
+
+    let [x, y, z] = cubes[0].coordinates().unwrap();
+    let expected = BiotSavart::new(
+        cubes[0].data.view(),
+        cubes[1].data.view(),
+        cubes[2].data.view(),
+        &x,
+        &y,
+        &z,
+    )
+    .unwrap()
+    .field()
+    .unwrap();
+    let found = density.solver().unwrap().field().unwrap();
+    assert_eq!(expected, found);
+    assert_eq!(density.atoms, cubes[0].atoms);
+}
+
+#[test]
+fn current_density_requires_shared_grid() {
+    let jx = temp_file("shared_jx", &cube_file([3, 4, 5], &values(60)));
+    let jy = temp_file("shared_jy", &cube_file([3, 4, 2], &values(24)));
+    let result = CurrentDensity::from_cubes(&jx, &jy, &jx);
+    fs::remove_file(&jx).unwrap();
+    fs::remove_file(&jy).unwrap();
+
+    match result.unwrap_err() {
+        Error::InvalidFile { path, .. } => assert_eq!(PathBuf::from(path), jy),
+        err => panic!("unexpected error {:?}", err),
+    }
+}
+
+#[test]
+fn data_is_in_c_order() {
+    let data = Array3::from_shape_fn((2, 2, 2), |(i, j, k)| (i + 2 * j + 4 * k) as f64);
+    let path = temp_file("roundtrip", &cube_file([2, 2, 2], data.as_slice().unwrap()));
+    let cube = Cube::read(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+
+    assert_eq!(cube.data, data);
+}
@@ -0,0 +1,97 @@
+extern crate biot_savart;
+extern crate ndarray;
+
+mod common;
+
+use biot_savart::{BiotSavart, Error, HalfPlane, Units};
+use ndarray::prelude::*;
+
+use common::close;
+
+// J = (0, -x, 0), the current near the edge of a ring around the z axis,
+// clockwise seen from +z, which is diatropic for x > 0 and paratropic for x < 0
+fn shear(cor: &[f64]) -> [Array3<f64>; 3] {
+    let n = cor.len();
+    [
+        Array3::zeros((n, n, n)),
+        Array3::from_shape_fn((n, n, n), |(i, _, _)| -cor[i]),
+        Array3::zeros((n, n, n)),
+    ]
+}
+
+fn plane(origin: [f64; 3], length: f64) -> HalfPlane {
+    HalfPlane {
+        origin,
+        direction: [1.0, 0.0, 0.0],
+        up: [0.0, 0.0, 1.0],
+        length,
+        height: 1.0,
+        spacing: 0.25,
+    }
+}
+
+#[test]
+fn splits_diatropic_and_paratropic_currents() {
+    let cor: Vec<f64> = (0..9).map(|i| -2.0 + i as f64 * 0.5).collect();
+    let [jx, jy, jz] = shear(&cor);
+    let solver = BiotSavart::new(jx.view(), jy.view(), jz.view(), &cor, &cor, &cor).unwrap();
+    let prefactor = Units::Atomic.current_strength_prefactor();
+
+    // J · n = x over the 2 high plane, from x = 0.5 to 1.5
+    let strength = solver
+        .current_strength(&plane([0.5, 0.0, 0.0], 1.0))
+        .unwrap();
+    assert!(close(strength.total, 2.0 * prefactor));
+    assert_eq!(strength.paratropic, 0.0);
+    assert_eq!(strength.distances.len(), 5);
+    assert_eq!(strength.profile.len(), 5);
+    for (s, profile) in strength.distances.iter().zip(&strength.profile) {
+        assert!(close(*profile, 2.0 * (0.5 + s) * prefactor));
+    }
+
+    // from x = -1 to 1 the currents cancel
+    let strength = solver
+        .current_strength(&plane([-1.0, 0.0, 0.0], 2.0))
+        .unwrap();
+    assert!(close(strength.diatropic, prefactor));
+    assert!(close(strength.paratropic, -prefactor));
+    assert!(close(strength.total, 0.0));
+}
+
+#[test]
+fn vanishes_outside_of_the_grid() {
+    let cor: Vec<f64> = (0..9).map(|i| -2.0 + i as f64 * 0.5).collect();
+    let [jx, jy, jz] = shear(&cor);
+    let solver = BiotSavart::new(jx.view(), jy.view(), jz.view(), &cor, &cor, &cor)
+        .unwrap()
+        .units(Units::SI);
+
+    let strength = solver
+        .current_strength(&plane([0.0, 0.0, 0.0], 4.0))
+        .unwrap();
+    for (s, profile) in strength.distances.iter().zip(&strength.profile) {
+        if *s > 2.0 {
+            assert_eq!(*profile, 0.0);
+        } else {
+            assert!(close(*profile, 2e9 * s));
+        }
+    }
+}
+
+#[test]
+fn rejects_degenerate_planes() {
+    let cor = [-1.0, 0.0, 1.0];
+    let [jx, jy, jz] = shear(&cor);
+    let solver = BiotSavart::new(jx.view(), jy.view(), jz.view(), &cor, &cor, &cor).unwrap();
+
+    let mut parallel = plane([0.0; 3], 1.0);
+    parallel.up = [2.0, 0.0, 0.0];
+    let mut flat = plane([0.0; 3], 1.0);
+    flat.height = 0.0;
+    for plane in &[parallel, flat] {
+        match solver.current_strength(plane) {
+            Err(Error::InvalidPlane(_)) => {}
+            other => panic!("expected InvalidPlane, found {:?}", other.map(|s| s.total)),
+        }
+    }
+}
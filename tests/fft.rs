@@ -0,0 +1,86 @@
+extern crate biot_savart;
+extern crate ndarray;
+
+mod common;
+
+use biot_savart::{BiotSavart, Error, Solver};
+use ndarray::prelude::*;
+
+use common::current_density;
+
+fn max_abs_diff(a: &Array3<f64>, b: &Array3<f64>) -> f64 {
+    a.iter()
+        .zip(b.iter())
+        .fold(0.0, |max, (a, b)| f64::max(max, (a - b).abs()))
+}
+
+#[test]
+fn fft_matches_direct() {
+    let x: Vec<f64> = (0..7).map(|i| i as f64 * 0.4 - 1.0).collect();
+    let y: Vec<f64> = (0..9).map(|i| i as f64 * 0.3 - 1.2).collect();
+    let z: Vec<f64> = (0..8).map(|i| 2.0 - i as f64 * 0.5).collect();
+    let (jx, jy, jz) = current_density((x.len(), y.len(), z.len()));
+
+    let solver = BiotSavart::new(jx.view(), jy.view(), jz.view(), &x, &y, &z).unwrap();
+    let direct = solver.field().unwrap();
+    let fft = solver.solver(Solver::Fft).field().unwrap();
+
+    let scale = direct.0.iter().fold(0.0, |max, b| f64::max(max, b.abs()));
+    assert!(max_abs_diff(&direct.0, &fft.0) < 1e-10 * scale);
+    assert!(max_abs_diff(&direct.1, &fft.1) < 1e-10 * scale);
+    assert!(max_abs_diff(&direct.2, &fft.2) < 1e-10 * scale);
+}
+
+#[test]
+fn fft_matches_direct_on_plane() {
+    let x: Vec<f64> = (0..6).map(|i| i as f64 * 0.5).collect();
+    let y = vec![0.0];
+    let z: Vec<f64> = (0..10).map(|i| i as f64 * 0.25).collect();
+    let (jx, jy, jz) = current_density((x.len(), y.len(), z.len()));
+
+    let solver = BiotSavart::new(jx.view(), jy.view(), jz.view(), &x, &y, &z).unwrap();
+    let direct = solver.field().unwrap();
+    let fft = solver.solver(Solver::Fft).field().unwrap();
+
+    let scale = direct.1.iter().fold(0.0, |max, b| f64::max(max, b.abs()));
+    assert!(max_abs_diff(&direct.0, &fft.0) < 1e-10 * scale);
+    assert!(max_abs_diff(&direct.1, &fft.1) < 1e-10 * scale);
+    assert!(max_abs_diff(&direct.2, &fft.2) < 1e-10 * scale);
+}
+
+#[test]
+fn fft_requires_uniform_grid() {
+    let x = vec![0.0, 0.5, 1.5, 2.0];
+    let (jx, jy, jz) = current_density((x.len(), x.len(), x.len()));
+
+    let result = BiotSavart::new(jx.view(), jy.view(), jz.view(), &x, &x, &x)
+        .unwrap()
+        .solver(Solver::Fft)
+        .field();
+    assert_eq!(result.unwrap_err(), Error::NonUniform);
+}
+
+#[test]
+fn fft_only_takes_grid_of_j() {
+    let x: Vec<f64> = (0..5).map(|i| i as f64 * 0.5).collect();
+    let (jx, jy, jz) = current_density((x.len(), x.len(), x.len()));
+    let solver = BiotSavart::new(jx.view(), jy.view(), jz.view(), &x, &x, &x).unwrap();
+    let direct = solver.field().unwrap();
+    let solver = solver.solver(Solver::Fft);
+
+    let fft = solver.field_on_grid(&x, &x, &x).unwrap();
+    let scale = direct.0.iter().fold(0.0, |max, b| f64::max(max, b.abs()));
+    assert!(max_abs_diff(&direct.0, &fft.0) < 1e-10 * scale);
+    assert!(max_abs_diff(&direct.1, &fft.1) < 1e-10 * scale);
+    assert!(max_abs_diff(&direct.2, &fft.2) < 1e-10 * scale);
+
+    assert_eq!(
+        solver.field_on_grid(&x, &x, &[0.25]).unwrap_err(),
+        Error::FftOffGrid
+    );
+    let points = array![[0.25, 0.5, 0.75]];
+    assert_eq!(
+        solver.field_at(points.view()).unwrap_err(),
+        Error::FftOffGrid
+    );
+}
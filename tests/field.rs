@@ -0,0 +1,103 @@
+extern crate biot_savart;
+extern crate ndarray;
+
+mod common;
+
+use std::fs;
+
+use biot_savart::{Atom, Cube, Error, VectorField};
+use ndarray::prelude::*;
+
+use common::temp_path;
+
+fn components(shape: (usize, usize, usize)) -> (Array3<f64>, Array3<f64>, Array3<f64>) {
+    let x = Array3::from_shape_fn(shape, |(i, j, k)| (i + 2 * j + 3 * k) as f64 * 0.5 - 1.0);
+    let y = Array3::from_shape_fn(shape, |(i, j, k)| (3 * i + j + k) as f64 * 0.25);
+    let z = Array3::from_shape_fn(shape, |(i, j, k)| (i * j + k) as f64 - 2.0);
+    (x, y, z)
+}
+
+#[test]
+fn cubes_read_back() {
+    let x_cor = [-1.0, -0.5, 0.0];
+    let y_cor = [0.0, 0.25];
+    let z_cor = [2.0, 1.8, 1.6, 1.4];
+    let (x, y, z) = components((3, 2, 4));
+    let atoms = vec![Atom {
+        number: 8,
+        charge: 8.0,
+        position: [0.5, 0.0, -0.25],
+    }];
+
+    let field = VectorField::new(x.view(), y.view(), z.view(), &x_cor, &y_cor, &z_cor).unwrap();
+    let paths = field.write_cubes(temp_path("b"), "B", &atoms).unwrap();
+    let cubes: Vec<Cube> = paths.iter().map(|path| Cube::read(path).unwrap()).collect();
+    for path in &paths {
+        fs::remove_file(path).unwrap();
+    }
+
+    assert_eq!(paths.len(), 4);
+    assert!(paths[3].to_str().unwrap().ends_with("b_norm.cube"));
+    let norm = field.norm();
+    for (cube, expected) in cubes.iter().zip(&[x, y, z, norm]) {
+        assert_eq!(cube.atoms, atoms);
+        assert_eq!(cube.coordinates().unwrap()[2].len(), 4);
+        assert!((cube.axes[2][2] + 0.2).abs() < 1e-6);
+        for (found, expected) in cube.data.iter().zip(expected.iter()) {
+            assert!((found - expected).abs() <= 1e-5 * expected.abs());
+        }
+    }
+}
+
+#[test]
+fn cubes_require_uniform_grid() {
+    let cor = [0.0, 0.5, 1.5];
+    let (x, y, z) = components((3, 3, 3));
+    let field = VectorField::new(x.view(), y.view(), z.view(), &cor, &cor, &cor).unwrap();
+    let result = field.write_cubes(temp_path("nonuniform"), "B", &[]);
+    assert_eq!(result.unwrap_err(), Error::NonUniform);
+    let result = field.write_vti(temp_path("nonuniform.vti"), "B");
+    assert_eq!(result.unwrap_err(), Error::NonUniform);
+}
+
+#[test]
+fn vtr_lists_coordinates_and_x_runs_fastest() {
+    let x_cor = [0.0, 0.5, 1.5];
+    let y_cor = [1.0];
+    let z_cor = [-1.0, 1.0];
+    let (x, y, z) = components((3, 1, 2));
+    let field = VectorField::new(x.view(), y.view(), z.view(), &x_cor, &y_cor, &z_cor).unwrap();
+
+    let path = temp_path("b.vtr");
+    field.write_vtr(&path, "B").unwrap();
+    let contents = fs::read_to_string(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+
+    assert!(contents.contains("<RectilinearGrid WholeExtent=\"0 2 0 0 0 1\">"));
+    assert!(contents.contains("Name=\"B\" NumberOfComponents=\"3\""));
+    assert!(contents.contains("Name=\"B_norm\""));
+    let vectors: Vec<Vec<f64>> = contents
+        .lines()
+        .map(|line| line.split_whitespace().map(|v| v.parse::<f64>()).collect())
+        .filter_map(|values: Result<Vec<f64>, _>| values.ok())
+        .filter(|values| values.len() == 3)
+        .collect();
+    assert_eq!(vectors.len(), 6);
+    assert_eq!(vectors[1], vec![x[[1, 0, 0]], y[[1, 0, 0]], z[[1, 0, 0]]]);
+    assert_eq!(vectors[3], vec![x[[0, 0, 1]], y[[0, 0, 1]], z[[0, 0, 1]]]);
+}
+
+#[test]
+fn vti_has_origin_and_spacing() {
+    let cor = [0.0, 0.5, 1.0];
+    let (x, y, z) = components((3, 3, 3));
+    let field = VectorField::new(x.view(), y.view(), z.view(), &cor, &cor, &cor).unwrap();
+
+    let path = temp_path("b.vti");
+    field.write_vti(&path, "B").unwrap();
+    let contents = fs::read_to_string(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+
+    assert!(contents.contains("Origin=\"0e0 0e0 0e0\" Spacing=\"5e-1 5e-1 5e-1\""));
+    assert!(!contents.contains("<Coordinates>"));
+}
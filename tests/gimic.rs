@@ -0,0 +1,119 @@
+extern crate biot_savart;
+extern crate ndarray;
+
+mod common;
+
+use std::fs;
+
+use biot_savart::{CurrentDensity, Error, Units};
+
+use common::temp_path;
+
+fn j(i: usize, j: usize, k: usize) -> [f64; 3] {
+    [i as f64 + 0.5, j as f64 * 2.0, -(k as f64)]
+}
+
+fn read(name: &str, contents: &str) -> biot_savart::Result<CurrentDensity> {
+    let path = temp_path(name);
+    fs::write(&path, contents).unwrap();
+    let density = CurrentDensity::from_gimic(&path);
+    fs::remove_file(&path).unwrap();
+    density
+}
+
+#[test]
+fn text_in_any_order() {
+    let cor = [[-1.0, 0.0, 1.0], [0.0, 0.5, 1.0], [2.0, 2.25, 2.5]];
+    let mut lines = vec!["# x y z jx jy jz".to_string()];
+    // z runs fastest here, unlike in the files GIMIC writes
+    for i in 0..3 {
+        for jj in 0..3 {
+            for k in 0..3 {
+                let v = j(i, jj, k);
+                lines.push(format!(
+                    "{:16.8E} {:16.8E} {:16.8E} {:16.8E} {:16.8E} {:16.8E}",
+                    cor[0][i], cor[1][jj], cor[2][k], v[0], v[1], v[2]
+                ));
+            }
+            lines.push(String::new());
+        }
+    }
+    let density = read("jvec.txt", &lines.join("\n")).unwrap();
+
+    assert_eq!(density.units, Units::Atomic);
+    assert_eq!(density.x_cor, cor[0]);
+    assert_eq!(density.z_cor, cor[2]);
+    assert_eq!(density.jx[[2, 0, 1]], j(2, 0, 1)[0]);
+    assert_eq!(density.jy[[1, 2, 0]], j(1, 2, 0)[1]);
+    assert_eq!(density.jz[[0, 1, 2]], j(0, 1, 2)[2]);
+    assert!(density.solver().is_ok());
+}
+
+#[test]
+fn text_has_to_be_a_grid() {
+    let contents = "0 0 0 1 1 1\n1 0 0 1 1 1\n0 1 0 1 1 1\n";
+    match read("jvec_missing.txt", contents) {
+        Err(Error::InvalidFile { message, .. }) => assert!(message.contains("grid")),
+        result => panic!("expected InvalidFile, found {:?}", result),
+    }
+    match read("jvec_columns.txt", "0 0 0 1 1\n") {
+        Err(Error::InvalidFile { message, .. }) => assert!(message.contains("line 1")),
+        result => panic!("expected InvalidFile, found {:?}", result),
+    }
+}
+
+#[test]
+fn vti_runs_with_x_fastest() {
+    let mut values = Vec::new();
+    for k in 0..2 {
+        for jj in 0..3 {
+            for i in 0..4 {
+                let v = j(i, jj, k);
+                values.push(format!("{} {} {}", v[0], v[1], v[2]));
+            }
+        }
+    }
+    let contents = format!(
+        r#"<?xml version="1.0"?>
+<VTKFile type="ImageData" version="0.1" byte_order="LittleEndian">
+  <ImageData WholeExtent="0 3 0 2 0 1" Origin="-1.5 -1.0 0.0" Spacing="1.0 1.0 0.5">
+    <Piece Extent="0 3 0 2 0 1">
+      <PointData Scalars="scalars" Vectors="vectors">
+        <DataArray type="Float64" Name="scalars" format="ascii">
+          {}
+        </DataArray>
+        <DataArray type="Float64" Name="vectors" NumberOfComponents="3" format="ascii">
+          {}
+        </DataArray>
+      </PointData>
+    </Piece>
+  </ImageData>
+</VTKFile>
+"#,
+        vec!["0"; 24].join(" "),
+        values.join("\n")
+    );
+    let density = read("jvec.vti", &contents).unwrap();
+
+    assert_eq!(density.x_cor, vec![-1.5, -0.5, 0.5, 1.5]);
+    assert_eq!(density.z_cor, vec![0.0, 0.5]);
+    assert_eq!(density.jx.dim(), (4, 3, 2));
+    assert_eq!(density.jx[[3, 1, 0]], j(3, 1, 0)[0]);
+    assert_eq!(density.jy[[2, 2, 1]], j(2, 2, 1)[1]);
+    assert_eq!(density.jz[[0, 0, 1]], j(0, 0, 1)[2]);
+}
+
+#[test]
+fn vti_has_to_be_ascii() {
+    let contents = r#"<VTKFile type="ImageData">
+  <ImageData WholeExtent="0 1 0 1 0 1" Origin="0 0 0" Spacing="1 1 1">
+    <PointData>
+      <DataArray type="Float64" NumberOfComponents="3" format="binary">AAAA</DataArray>
+    </PointData>
+  </ImageData>
+</VTKFile>"#;
+    match read("binary.vti", contents) {
+        Err(Error::InvalidFile { message, .. }) => assert!(message.contains("binary")),
+        result => panic!("expected InvalidFile, found {:?}", result),
+    }
+}
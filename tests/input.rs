@@ -0,0 +1,94 @@
+extern crate biot_savart;
+extern crate ndarray;
+
+use biot_savart::{BiotSavart, Error};
+use ndarray::prelude::*;
+
+const X: [f64; 3] = [0.0, 0.5, 1.0];
+const Y: [f64; 4] = [-1.0, 0.0, 1.0, 2.0];
+const Z: [f64; 2] = [0.0, 0.25];
+
+fn check(jx: &Array3<f64>, jy: &Array3<f64>, jz: &Array3<f64>, cor: [&[f64]; 3]) -> Error {
+    match BiotSavart::new(jx.view(), jy.view(), jz.view(), cor[0], cor[1], cor[2]) {
+        Ok(_) => panic!("invalid input accepted"),
+        Err(err) => err,
+    }
+}
+
+#[test]
+fn accepts_increasing_and_decreasing_coordinates() {
+    let j = Array3::ones((3, 4, 2));
+    let z = [0.25, 0.0];
+    assert!(BiotSavart::new(j.view(), j.view(), j.view(), &X, &Y, &Z).is_ok());
+    assert!(BiotSavart::new(j.view(), j.view(), j.view(), &X, &Y, &z).is_ok());
+}
+
+#[test]
+fn rejects_ragged_components() {
+    let j = Array3::ones((3, 4, 2));
+    let ragged = Array3::ones((3, 3, 2));
+    assert_eq!(
+        check(&j, &ragged, &j, [&X, &Y, &Z]),
+        Error::ShapeMismatch {
+            name: "jy",
+            expected: vec![3, 4, 2],
+            found: vec![3, 3, 2],
+        }
+    );
+
+    // consistent components that do not match the coordinates
+    let transposed = Array3::ones((4, 3, 2));
+    assert_eq!(
+        check(&transposed, &transposed, &transposed, [&X, &Y, &Z]),
+        Error::ShapeMismatch {
+            name: "jx",
+            expected: vec![3, 4, 2],
+            found: vec![4, 3, 2],
+        }
+    );
+}
+
+#[test]
+fn rejects_empty_axes() {
+    let j = Array3::ones((3, 0, 2));
+    assert_eq!(check(&j, &j, &j, [&X, &[], &Z]), Error::EmptyGrid("y_cor"));
+}
+
+#[test]
+fn rejects_non_monotonic_coordinates() {
+    let j = Array3::ones((3, 4, 2));
+    let x = [0.0, 1.0, 0.5];
+    assert_eq!(
+        check(&j, &j, &j, [&x, &Y, &Z]),
+        Error::NonMonotonic("x_cor")
+    );
+    // repeated coordinates are not strictly monotonic either
+    let y = [-1.0, 0.0, 0.0, 2.0];
+    assert_eq!(
+        check(&j, &j, &j, [&X, &y, &Z]),
+        Error::NonMonotonic("y_cor")
+    );
+}
+
+#[test]
+fn rejects_non_finite_coordinates() {
+    let j = Array3::ones((3, 4, 2));
+    let z = [0.0, std::f64::NAN];
+    assert_eq!(check(&j, &j, &j, [&X, &Y, &z]), Error::NonFinite("z_cor"));
+    let x = [0.0, 0.5, std::f64::INFINITY];
+    assert_eq!(check(&j, &j, &j, [&x, &Y, &Z]), Error::NonFinite("x_cor"));
+}
+
+#[test]
+fn rejects_non_finite_current_density() {
+    let j = Array3::ones((3, 4, 2));
+    let mut nan = j.clone();
+    nan[[2, 1, 0]] = std::f64::NAN;
+    assert_eq!(check(&j, &j, &nan, [&X, &Y, &Z]), Error::NonFinite("jz"));
+    let mut infinite = j.clone();
+    infinite[[0, 3, 1]] = std::f64::NEG_INFINITY;
+    assert_eq!(
+        check(&infinite, &j, &j, [&X, &Y, &Z]),
+        Error::NonFinite("jx")
+    );
+}
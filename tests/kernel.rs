@@ -0,0 +1,150 @@
+extern crate biot_savart;
+extern crate ndarray;
+
+mod common;
+
+use biot_savart::{Backend, BiotSavart};
+use ndarray::prelude::*;
+
+use common::current_density;
+
+const BACKENDS: [Backend; 5] = [
+    Backend::Auto,
+    Backend::Scalar,
+    Backend::Sse2,
+    Backend::Sse41,
+    Backend::Avx2,
+];
+
+fn coordinates(n: usize, h: f64) -> Vec<f64> {
+    (0..n).map(|i| i as f64 * h - 0.5 * n as f64 * h).collect()
+}
+
+// Σ J × r / r³ dV over all grid points but the target, one point at a time
+fn reference(j: [&Array3<f64>; 3], cor: [&[f64]; 3], h: [f64; 3], b_r: [f64; 3]) -> [f64; 3] {
+    let mut result = [0f64; 3];
+    for ((xi, yi, zi), jx) in j[0].indexed_iter() {
+        let jy = j[1][[xi, yi, zi]];
+        let jz = j[2][[xi, yi, zi]];
+        let rx = b_r[0] - cor[0][xi];
+        let ry = b_r[1] - cor[1][yi];
+        let rz = b_r[2] - cor[2][zi];
+        let r2 = rx * rx + ry * ry + rz * rz;
+        if r2 == 0.0 {
+            continue;
+        }
+        // a dimension with a single point has width 1
+        let width = |n: usize, h: f64| if n > 1 { h } else { 1.0 };
+        let dv = width(cor[0].len(), h[0]) * width(cor[1].len(), h[1]) * width(cor[2].len(), h[2]);
+        let scale = dv / (r2 * r2.sqrt());
+        result[0] += (jy * rz - ry * jz) * scale;
+        result[1] += (rx * jz - jx * rz) * scale;
+        result[2] += (jx * ry - rx * jy) * scale;
+    }
+    result
+}
+
+fn check(shape: (usize, usize, usize), fortran: bool) {
+    let h = [0.3, 0.25, 0.2];
+    let x = coordinates(shape.0, h[0]);
+    let y = coordinates(shape.1, h[1]);
+    let z = coordinates(shape.2, h[2]);
+    let (mut jx, mut jy, mut jz) = current_density(shape);
+    if fortran {
+        for j in [&mut jx, &mut jy, &mut jz].iter_mut() {
+            let mut f = Array3::zeros(shape.f());
+            f.assign(j);
+            **j = f;
+        }
+    }
+
+    // targets on the grid, between grid points and outside of the grid
+    let points = array![
+        [x[0], y[shape.1 / 2], z[shape.2 - 1]],
+        [x[shape.0 - 1], y[0], z[shape.2 / 2]],
+        [0.013, -0.021, 0.037],
+        [1.5, -2.0, 3.0 + shape.2 as f64 * h[2]],
+    ];
+    let expected: Vec<[f64; 3]> = points
+        .genrows()
+        .into_iter()
+        .map(|p| reference([&jx, &jy, &jz], [&x, &y, &z], h, [p[0], p[1], p[2]]))
+        .collect();
+
+    for backend in BACKENDS.iter().filter(|backend| backend.is_supported()) {
+        let b = BiotSavart::new(jx.view(), jy.view(), jz.view(), &x, &y, &z)
+            .unwrap()
+            .units(biot_savart::Units::SI)
+            .backend(*backend)
+            .field_at(points.view())
+            .unwrap();
+
+        for (row, expected) in b.genrows().into_iter().zip(&expected) {
+            for a in 0..3 {
+                // SI prefactor of 1e-7
+                let value = row[a] / 1e-7;
+                let tolerance = 1e-12 * (1.0 + expected[a].abs());
+                assert!(
+                    (value - expected[a]).abs() < tolerance,
+                    "{} backend, shape {:?}: {} != {}",
+                    backend,
+                    shape,
+                    value,
+                    expected[a]
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn odd_lengths_match_scalar_reference() {
+    for &nz in &[1, 2, 3, 5, 7, 9, 31, 63, 65, 67, 129] {
+        check((3, 5, nz), false);
+    }
+}
+
+#[test]
+fn fortran_order_matches_scalar_reference() {
+    for &nz in &[1, 3, 7, 65, 67] {
+        check((5, 3, nz), true);
+    }
+}
+
+#[test]
+fn single_point_grids_match_scalar_reference() {
+    check((1, 1, 1), false);
+    check((1, 1, 13), false);
+    check((7, 1, 1), false);
+}
+
+#[test]
+fn backends_agree() {
+    let shape = (4, 6, 71);
+    let x = coordinates(shape.0, 0.4);
+    let y = coordinates(shape.1, 0.3);
+    let z = coordinates(shape.2, 0.05);
+    let (jx, jy, jz) = current_density(shape);
+    let field = |backend: Backend| {
+        BiotSavart::new(jx.view(), jy.view(), jz.view(), &x, &y, &z)
+            .unwrap()
+            .backend(backend)
+            .field_on_grid(&x[1..3], &y[2..3], &z[30..37])
+            .unwrap()
+    };
+
+    let scalar = field(Backend::Scalar);
+    let scale = scalar.0.iter().fold(0.0, |max, b| f64::max(max, b.abs()));
+    for backend in BACKENDS.iter().filter(|backend| backend.is_supported()) {
+        let b = field(*backend);
+        for (expected, found) in [(&scalar.0, &b.0), (&scalar.1, &b.1), (&scalar.2, &b.2)].iter() {
+            for (expected, found) in expected.iter().zip(found.iter()) {
+                assert!(
+                    (expected - found).abs() <= 1e-12 * scale,
+                    "{} backend",
+                    backend
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,86 @@
+extern crate biot_savart;
+extern crate ndarray;
+
+mod common;
+
+use biot_savart::{BiotSavart, MagnetizabilityTensor};
+use ndarray::prelude::*;
+
+use common::close;
+
+// Current circulating around `axis` through the origin, clockwise seen from
+// +axis, which is diamagnetic for a field along +axis
+fn circulating(cor: &[f64], axis: usize, strength: f64) -> [Array3<f64>; 3] {
+    let n = cor.len();
+    let (p, q) = ((axis + 1) % 3, (axis + 2) % 3);
+    let weight = |r: [f64; 3]| strength * (-(r[0] * r[0] + r[1] * r[1] + r[2] * r[2])).exp();
+    let component = |a: usize| {
+        Array3::from_shape_fn((n, n, n), |(i, j, k)| {
+            let r = [cor[i], cor[j], cor[k]];
+            if a == p {
+                r[q] * weight(r)
+            } else if a == q {
+                -r[p] * weight(r)
+            } else {
+                0.0
+            }
+        })
+    };
+    [component(0), component(1), component(2)]
+}
+
+#[test]
+fn diagonal_for_circulating_currents() {
+    let cor: Vec<f64> = (0..13).map(|i| -3.0 + i as f64 * 0.5).collect();
+    let j = [
+        circulating(&cor, 0, 1.0),
+        circulating(&cor, 1, 2.0),
+        circulating(&cor, 2, 4.0),
+    ];
+    let solvers: Vec<BiotSavart> = j
+        .iter()
+        .map(|j| BiotSavart::new(j[0].view(), j[1].view(), j[2].view(), &cor, &cor, &cor).unwrap())
+        .collect();
+    let center = [0.0; 3];
+    let xi = MagnetizabilityTensor::new([&solvers[0], &solvers[1], &solvers[2]], center);
+
+    for (b, solver) in solvers.iter().enumerate() {
+        let m = solver.magnetization(center);
+        for (row, m) in xi.tensor.iter().zip(&m) {
+            assert!(close(row[b], *m));
+        }
+    }
+    let diagonal = [xi.tensor[0][0], xi.tensor[1][1], xi.tensor[2][2]];
+    assert!(diagonal.iter().all(|x| *x < 0.0));
+    assert!(close(diagonal[1], 2.0 * diagonal[0]));
+    assert!(close(diagonal[2], 4.0 * diagonal[0]));
+    assert!(close(xi.isotropic(), 7.0 / 3.0 * diagonal[0]));
+
+    // the most diamagnetic axis is z
+    let (values, vectors) = xi.eigen();
+    assert!(close(values[0], diagonal[2]));
+    assert!(close(vectors[0][2].abs(), 1.0));
+    assert!(close(
+        xi.anisotropy(),
+        diagonal[0] - 0.5 * (diagonal[2] + diagonal[1])
+    ));
+    let part = xi.anisotropic_part();
+    assert!(close(part[0][0] + part[1][1] + part[2][2], 0.0));
+}
+
+#[test]
+fn eigenvectors_of_a_rotated_tensor() {
+    let xi = MagnetizabilityTensor {
+        tensor: [[-3.0, 1.0, 0.0], [1.0, -3.0, 0.0], [0.0, 0.0, -1.0]],
+        center: [0.0; 3],
+    };
+    let (values, vectors) = xi.eigen();
+    for (found, expected) in values.iter().zip(&[-4.0, -2.0, -1.0]) {
+        assert!(close(*found, *expected));
+    }
+    let h = 0.5f64.sqrt();
+    assert!(close(vectors[0][0].abs(), h) && close(vectors[0][1], -vectors[0][0]));
+    assert!(close(vectors[1][0], vectors[1][1]) && close(vectors[1][0].abs(), h));
+    assert!(close(vectors[2][2].abs(), 1.0));
+    assert!(close(xi.anisotropy(), 2.0));
+}
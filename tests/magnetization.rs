@@ -0,0 +1,92 @@
+extern crate biot_savart;
+extern crate ndarray;
+
+mod common;
+
+use biot_savart::{BiotSavart, Units};
+use ndarray::prelude::*;
+
+use common::close;
+
+#[test]
+fn density_integrates_to_the_magnetization() {
+    // unevenly spaced along x, so the cells differ in volume
+    let x_cor = [-1.0, -0.25, 0.0, 0.5, 2.0];
+    let y_cor = [-1.0, 0.0, 1.0];
+    let z_cor = [-0.5, 0.5];
+    let shape = (x_cor.len(), y_cor.len(), z_cor.len());
+    let jx = Array3::from_shape_fn(shape, |(_, j, k)| -y_cor[j] + 0.1 * z_cor[k]);
+    let jy = Array3::from_shape_fn(shape, |(i, _, _)| x_cor[i]);
+    let jz = Array3::from_shape_fn(shape, |(i, j, _)| 0.3 * x_cor[i] * y_cor[j]);
+    let solver = BiotSavart::new(jx.view(), jy.view(), jz.view(), &x_cor, &y_cor, &z_cor)
+        .unwrap()
+        .units(Units::SI);
+    let center = [0.5, -0.5, 0.0];
+
+    let (m, (m_x, m_y, m_z)) = solver.magnetization_density(center);
+    assert_eq!(m, solver.magnetization(center));
+    assert_eq!(m_x.dim(), shape);
+
+    // the outermost cells are symmetric around their point
+    let widths = |cor: &[f64]| -> Vec<f64> {
+        let n = cor.len();
+        (0..n)
+            .map(|i| match i {
+                0 => cor[1] - cor[0],
+                _ if i == n - 1 => cor[n - 1] - cor[n - 2],
+                _ => 0.5 * (cor[i + 1] - cor[i - 1]),
+            })
+            .collect()
+    };
+    let (dx, dy, dz) = (widths(&x_cor), widths(&y_cor), widths(&z_cor));
+    let integral = |density: &Array3<f64>| -> f64 {
+        density
+            .indexed_iter()
+            .map(|((i, j, k), value)| value * dx[i] * dy[j] * dz[k])
+            .sum()
+    };
+    assert!(close(integral(&m_x), m[0]));
+    assert!(close(integral(&m_y), m[1]));
+    assert!(close(integral(&m_z), m[2]));
+
+    // M = r × J / 2 at each point
+    let (i, j, k) = (3, 0, 1);
+    let r = [
+        x_cor[i] - center[0],
+        y_cor[j] - center[1],
+        z_cor[k] - center[2],
+    ];
+    let (jx, jy, jz) = (jx[[i, j, k]], jy[[i, j, k]], jz[[i, j, k]]);
+    assert!(close(m_x[[i, j, k]], 0.5 * (r[1] * jz - r[2] * jy)));
+    assert!(close(m_y[[i, j, k]], 0.5 * (r[2] * jx - r[0] * jz)));
+    assert!(close(m_z[[i, j, k]], 0.5 * (r[0] * jy - r[1] * jx)));
+}
+
+#[test]
+fn loop_magnetization_points_along_its_axis() {
+    // J circulates counterclockwise around z seen from +z, so m = k ∫ r × J dV
+    // points along +z, like B at the center. Before the sign convention could be
+    // chosen, the negative was returned.
+    let cor: Vec<f64> = (0..9).map(|i| -2.0 + i as f64 * 0.5).collect();
+    let n = cor.len();
+    let weight = |i: usize, j: usize, k: usize| {
+        (-(cor[i] * cor[i] + cor[j] * cor[j] + cor[k] * cor[k])).exp()
+    };
+    let jx = Array3::from_shape_fn((n, n, n), |(i, j, k)| -cor[j] * weight(i, j, k));
+    let jy = Array3::from_shape_fn((n, n, n), |(i, j, k)| cor[i] * weight(i, j, k));
+    let jz = Array3::zeros((n, n, n));
+    let solver = BiotSavart::new(jx.view(), jy.view(), jz.view(), &cor, &cor, &cor)
+        .unwrap()
+        .units(Units::SI);
+
+    // m_z = 1/2 ∫ (x² + y²) w dV, with cells of 0.5³
+    let expected: f64 = Array3::from_shape_fn((n, n, n), |(i, j, k)| {
+        0.5 * (cor[i] * cor[i] + cor[j] * cor[j]) * weight(i, j, k) * 0.125
+    })
+    .sum();
+    let m = solver.magnetization([0.0; 3]);
+    assert!(expected > 0.0);
+    assert!(close(m[2], expected));
+    assert!(close(m[0], 0.0));
+    assert!(close(m[1], 0.0));
+}
@@ -0,0 +1,100 @@
+extern crate biot_savart;
+extern crate ndarray;
+
+use biot_savart::{BiotSavart, Units};
+use ndarray::prelude::*;
+
+fn close(a: f64, b: f64, scale: f64) -> bool {
+    (a - b).abs() <= 1e-8 * scale
+}
+
+// Current circulating poloidally around a ring of radius 1.5 in the xy plane, as
+// in a toroidal solenoid. J = ∇ × (g (-y, x, 0)), which is divergence-free, and
+// has no net current or magnetic dipole, but a toroidal moment along z.
+fn toroidal(x_cor: &[f64], z_cor: &[f64]) -> [Array3<f64>; 3] {
+    let shape = (x_cor.len(), x_cor.len(), z_cor.len());
+    let component = |a: usize| {
+        Array3::from_shape_fn(shape, |(i, j, k)| {
+            let (x, y, z) = (x_cor[i], x_cor[j], z_cor[k]);
+            let rho2 = x * x + y * y;
+            let g = (-(rho2 - 2.25).powi(2) / 2.25 - 4.0 * z * z).exp();
+            match a {
+                0 => 8.0 * x * z * g,
+                1 => 8.0 * y * z * g,
+                _ => (2.0 - 4.0 * rho2 * (rho2 - 2.25) / 2.25) * g,
+            }
+        })
+    };
+    [component(0), component(1), component(2)]
+}
+
+#[test]
+fn toroidal_moment_of_a_toroidal_solenoid() {
+    let x_cor: Vec<f64> = (0..41).map(|i| -4.0 + i as f64 * 0.2).collect();
+    let z_cor: Vec<f64> = (0..25).map(|i| -2.4 + i as f64 * 0.2).collect();
+    let [jx, jy, jz] = toroidal(&x_cor, &z_cor);
+    let solver = BiotSavart::new(jx.view(), jy.view(), jz.view(), &x_cor, &x_cor, &z_cor).unwrap();
+
+    let moments = solver.multipoles([0.0; 3]);
+    let t = moments.toroidal[2].abs();
+    assert!(t > 0.1);
+    let m = solver.magnetization([0.0; 3]);
+    for (dipole, m) in moments.dipole.iter().zip(&m) {
+        assert!(close(*dipole, *m, t));
+    }
+    assert!(close(moments.toroidal[0], 0.0, t));
+    assert!(close(moments.toroidal[1], 0.0, t));
+    for a in moments.net_current.iter().chain(&moments.dipole) {
+        assert!(close(*a, 0.0, t));
+    }
+
+    // Q is traceless and symmetric, and vanishes by symmetry
+    let q = moments.quadrupole;
+    assert!(close(q[0][0] + q[1][1] + q[2][2], 0.0, t));
+    for (a, row) in q.iter().enumerate() {
+        for (b, value) in row.iter().enumerate() {
+            assert!(close(*value, q[b][a], t));
+            assert!(close(*value, 0.0, t));
+        }
+    }
+
+    // without net current and dipole, the moments do not depend on the center
+    let shifted = solver.multipoles([0.3, -0.2, 0.1]);
+    assert_eq!(shifted.center, [0.3, -0.2, 0.1]);
+    for (shifted, toroidal) in shifted.toroidal.iter().zip(&moments.toroidal) {
+        assert!(close(*shifted, *toroidal, t));
+    }
+    for (shifted, q) in shifted.quadrupole.iter().zip(&q) {
+        for (shifted, q) in shifted.iter().zip(q) {
+            assert!(close(*shifted, *q, t));
+        }
+    }
+}
+
+#[test]
+fn dipole_moves_with_the_net_current() {
+    let cor = [-1.0, 0.0, 1.0];
+    let jx = Array3::from_elem((3, 3, 3), 2.0);
+    let jy = Array3::from_shape_fn((3, 3, 3), |(_, _, k)| cor[k]);
+    let jz = Array3::from_elem((3, 3, 3), -1.0);
+    let solver = BiotSavart::new(jx.view(), jy.view(), jz.view(), &cor, &cor, &cor)
+        .unwrap()
+        .units(Units::SI);
+
+    let moments = solver.multipoles([0.0; 3]);
+    // the outermost cells have width 1, so the grid has a volume of 27
+    let net = moments.net_current;
+    assert!(close(net[0], 54.0, 1.0) && close(net[1], 0.0, 1.0) && close(net[2], -27.0, 1.0));
+
+    // m - d × I / 2
+    let d = [0.5, 1.0, -2.0];
+    let expected = [
+        moments.dipole[0] - 0.5 * (d[1] * net[2] - d[2] * net[1]),
+        moments.dipole[1] - 0.5 * (d[2] * net[0] - d[0] * net[2]),
+        moments.dipole[2] - 0.5 * (d[0] * net[1] - d[1] * net[0]),
+    ];
+    let shifted = solver.multipoles(d);
+    for (found, expected) in shifted.dipole.iter().zip(&expected) {
+        assert!(close(*found, *expected, 100.0));
+    }
+}
@@ -0,0 +1,207 @@
+extern crate biot_savart;
+extern crate ndarray;
+
+mod common;
+
+use std::fs;
+use std::path::PathBuf;
+
+use biot_savart::{read_npy, read_npz, write_npy, CurrentDensity, Error, NpzWriter, VectorField};
+use ndarray::prelude::*;
+
+use common::temp_path;
+
+// Writes a version 1.0 .npy file of `values` with a header dict of `descr`,
+// `fortran_order` and `shape`.
+fn write_npy(name: &str, descr: &str, fortran_order: bool, shape: &str, values: &[u8]) -> PathBuf {
+    let mut header = format!(
+        "{{'descr': '{}', 'fortran_order': {}, 'shape': {}, }}",
+        descr,
+        if fortran_order { "True" } else { "False" },
+        shape
+    );
+    // the header is padded with spaces to end the preamble at a multiple of 64
+    while (10 + header.len() + 1) % 64 != 0 {
+        header.push(' ');
+    }
+    header.push('\n');
+
+    let mut bytes = b"\x93NUMPY\x01\x00".to_vec();
+    bytes.extend_from_slice(&(header.len() as u16).to_le_bytes());
+    bytes.extend_from_slice(header.as_bytes());
+    bytes.extend_from_slice(values);
+    let path = temp_path(name);
+    fs::write(&path, bytes).unwrap();
+    path
+}
+
+fn f8(values: &[f64]) -> Vec<u8> {
+    values
+        .iter()
+        .flat_map(|v| v.to_le_bytes().to_vec())
+        .collect()
+}
+
+#[test]
+fn reads_c_and_fortran_order() {
+    let values: Vec<f64> = (0..6).map(f64::from).collect();
+    let c = write_npy("c.npy", "<f8", false, "(2, 3)", &f8(&values));
+    let f = write_npy("f.npy", "<f8", true, "(2, 3)", &f8(&values));
+    let c_array = read_npy(&c).unwrap();
+    let f_array = read_npy(&f).unwrap();
+    fs::remove_file(&c).unwrap();
+    fs::remove_file(&f).unwrap();
+
+    assert_eq!(c_array.shape(), &[2, 3]);
+    assert_eq!(c_array[[1, 0]], 3.0);
+    assert_eq!(f_array.shape(), &[2, 3]);
+    assert_eq!(f_array[[1, 0]], 1.0);
+}
+
+#[test]
+fn reads_big_endian_float32() {
+    let values: Vec<u8> = [1.5f32, -2.0]
+        .iter()
+        .flat_map(|v| v.to_be_bytes().to_vec())
+        .collect();
+    let path = write_npy("f4.npy", ">f4", false, "(2,)", &values);
+    let array = read_npy(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+
+    assert_eq!(array, arr1(&[1.5, -2.0]).into_dyn());
+}
+
+#[test]
+fn rejects_integers() {
+    let path = write_npy("i8.npy", "<i8", false, "(1,)", &[0; 8]);
+    let result = read_npy(&path);
+    fs::remove_file(&path).unwrap();
+
+    match result {
+        Err(Error::InvalidFile { message, .. }) => assert!(message.contains("<i8")),
+        result => panic!("expected InvalidFile, found {:?}", result),
+    }
+}
+
+#[test]
+fn rejects_data_not_matching_shape() {
+    let values = f8(&[1.0, 2.0, 3.0, 4.0, 5.0]);
+    let short = write_npy("short.npy", "<f8", false, "(2, 3)", &values);
+    let long = write_npy("long.npy", "<f8", false, "(2, 2)", &values);
+    let huge = write_npy(
+        "huge.npy",
+        "<f8",
+        false,
+        "(4294967296, 4294967296)",
+        &values,
+    );
+    for path in [short, long, huge].iter() {
+        let result = read_npy(path);
+        fs::remove_file(path).unwrap();
+        match result {
+            Err(Error::InvalidFile { message, .. }) => {
+                assert!(message.contains("bytes of data") || message.contains("too large"))
+            }
+            result => panic!("expected InvalidFile, found {:?}", result),
+        }
+    }
+}
+
+#[test]
+fn current_density_from_npy() {
+    let j: Vec<f64> = (0..8).map(f64::from).collect();
+    let cor = [-1.0, 1.0];
+    let names = ["jx.npy", "jy.npy", "jz.npy", "x.npy", "y.npy", "z.npy"];
+    let paths: Vec<PathBuf> = names
+        .iter()
+        .enumerate()
+        .map(|(i, name)| match i {
+            0..=2 => write_npy(name, "<f8", false, "(2, 2, 2)", &f8(&j)),
+            _ => write_npy(name, "<f8", false, "(2,)", &f8(&cor)),
+        })
+        .collect();
+    let density = CurrentDensity::from_npy(
+        [&paths[0], &paths[1], &paths[2]],
+        [&paths[3], &paths[4], &paths[5]],
+    );
+    let swapped = CurrentDensity::from_npy(
+        [&paths[3], &paths[1], &paths[2]],
+        [&paths[0], &paths[4], &paths[5]],
+    );
+    for path in &paths {
+        fs::remove_file(path).unwrap();
+    }
+
+    let density = density.unwrap();
+    assert_eq!(density.jy[[1, 0, 1]], 5.0);
+    assert_eq!(density.z_cor, cor);
+    assert!(density.solver().is_ok());
+    assert!(swapped.is_err());
+}
+
+#[test]
+fn write_npy_reads_back() {
+    let array = Array3::from_shape_fn((2, 3, 4), |(i, j, k)| (i * 12 + j * 4 + k) as f64 * 0.5);
+    // a transposed view is written in logical order
+    let transposed = array.t();
+    let path = temp_path("written.npy");
+    write_npy(&path, transposed).unwrap();
+    let read = read_npy(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+
+    assert_eq!(read, transposed.into_dyn());
+}
+
+#[test]
+fn npz_reads_back() {
+    let x_cor = [0.0, 1.0];
+    let y_cor = [-1.0, 0.0, 1.0];
+    let z_cor = [2.0];
+    let b = Array3::from_shape_fn((2, 3, 1), |(i, j, _)| (i + j) as f64);
+    let field = VectorField::new(b.view(), b.view(), b.view(), &x_cor, &y_cor, &z_cor).unwrap();
+
+    let path = temp_path("b.npz");
+    let mut npz = NpzWriter::create(&path).unwrap();
+    npz.add_str("units", "au").unwrap();
+    npz.add_array("m", aview1(&[1.0, 2.0, 3.0])).unwrap();
+    field.add_to_npz(&mut npz, "B").unwrap();
+    npz.finish().unwrap();
+    let arrays = read_npz(&path, &["Bz", "m", "y_cor"]);
+    let missing = read_npz(&path, &["Bw"]);
+    // a string is not a float array
+    let units = read_npz(&path, &["units"]);
+    fs::remove_file(&path).unwrap();
+
+    let arrays = arrays.unwrap();
+    assert_eq!(arrays[0], b.into_dyn());
+    assert_eq!(arrays[1], arr1(&[1.0, 2.0, 3.0]).into_dyn());
+    assert_eq!(arrays[2], arr1(&y_cor).into_dyn());
+    match missing {
+        Err(Error::InvalidFile { message, .. }) => assert!(message.contains("'Bw'")),
+        result => panic!("expected InvalidFile, found {:?}", result),
+    }
+    match units {
+        Err(Error::InvalidFile { message, .. }) => assert!(message.contains("<U2")),
+        result => panic!("expected InvalidFile, found {:?}", result),
+    }
+}
+
+#[test]
+fn current_density_from_npz() {
+    let j = Array3::from_shape_fn((2, 2, 3), |(i, j, k)| (i + j + k) as f64);
+    let path = temp_path("j.npz");
+    let mut npz = NpzWriter::create(&path).unwrap();
+    for name in &["jx", "jy", "jz"] {
+        npz.add_array(name, j.view()).unwrap();
+    }
+    npz.add_array("x_cor", aview1(&[0.0, 1.0])).unwrap();
+    npz.add_array("y_cor", aview1(&[0.0, 1.0])).unwrap();
+    npz.add_array("z_cor", aview1(&[0.0, 1.0, 2.0])).unwrap();
+    npz.finish().unwrap();
+    let density = CurrentDensity::from_npz(&path);
+    fs::remove_file(&path).unwrap();
+
+    let density = density.unwrap();
+    assert_eq!(density.jz, j);
+    assert_eq!(density.z_cor, vec![0.0, 1.0, 2.0]);
+}
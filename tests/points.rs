@@ -0,0 +1,128 @@
+extern crate biot_savart;
+extern crate ndarray;
+
+mod common;
+
+use biot_savart::{BiotSavart, Error, Units};
+use ndarray::prelude::*;
+
+use common::{current_density, direct_field};
+
+// Grid with uneven spacing and a decreasing dimension
+fn grid() -> (Vec<f64>, Vec<f64>, Vec<f64>) {
+    let x = vec![-1.0, -0.4, 0.0, 0.3, 1.1];
+    let y = vec![0.9, 0.5, 0.2, -0.5];
+    let z = vec![-0.6, -0.1, 0.25, 0.5, 1.0, 1.2];
+    (x, y, z)
+}
+
+fn assert_matches(found: ArrayView1<f64>, expected: [f64; 3], scale: f64) {
+    for (found, expected) in found.iter().zip(&expected) {
+        assert!(
+            (found - expected).abs() <= 1e-12 * scale,
+            "{} != {}",
+            found,
+            expected
+        );
+    }
+}
+
+#[test]
+fn grid_points_match_direct_sum() {
+    let (x, y, z) = grid();
+    let (jx, jy, jz) = current_density((x.len(), y.len(), z.len()));
+    let solver = BiotSavart::new(jx.view(), jy.view(), jz.view(), &x, &y, &z)
+        .unwrap()
+        .units(Units::SI);
+
+    // every grid point of J, in an order unrelated to the grid
+    let total = x.len() * y.len() * z.len();
+    let indices: Vec<(usize, usize, usize)> = (0..total)
+        .map(|n| (n * 7) % total)
+        .map(|n| {
+            (
+                n / (y.len() * z.len()),
+                (n / z.len()) % y.len(),
+                n % z.len(),
+            )
+        })
+        .collect();
+    let points = Array2::from_shape_fn((total, 3), |(n, a)| {
+        let (i, j, k) = indices[n];
+        [x[i], y[j], z[k]][a]
+    });
+    let b = solver.field_at(points.view()).unwrap();
+
+    let prefactor = Units::SI.field_prefactor();
+    let expected: Vec<[f64; 3]> = points
+        .genrows()
+        .into_iter()
+        .map(|p| direct_field([&jx, &jy, &jz], [&x, &y, &z], [p[0], p[1], p[2]]))
+        .map(|b| [b[0] * prefactor, b[1] * prefactor, b[2] * prefactor])
+        .collect();
+    let scale = expected
+        .iter()
+        .flat_map(|b| b.iter())
+        .fold(0.0, |max, b| f64::max(max, b.abs()));
+    for (found, expected) in b.genrows().into_iter().zip(expected) {
+        assert_matches(found, expected, scale);
+    }
+
+    // and they are the values of B on the grid
+    let (b_x, b_y, b_z) = solver.field().unwrap();
+    for (found, &idx) in b.genrows().into_iter().zip(&indices) {
+        assert_matches(found, [b_x[idx], b_y[idx], b_z[idx]], scale);
+    }
+}
+
+#[test]
+fn points_off_the_grid_match_direct_sum() {
+    let (x, y, z) = grid();
+    let (jx, jy, jz) = current_density((x.len(), y.len(), z.len()));
+    let solver = BiotSavart::new(jx.view(), jy.view(), jz.view(), &x, &y, &z).unwrap();
+
+    // between grid points, on a grid plane, and outside of the grid
+    let points = array![[0.1, 0.3, 0.7], [-0.4, 0.05, 0.9], [2.5, -1.5, -3.0]];
+    let b = solver.field_at(points.view()).unwrap();
+    let prefactor = Units::Atomic.field_prefactor();
+    for (found, p) in b.genrows().into_iter().zip(points.genrows()) {
+        let expected = direct_field([&jx, &jy, &jz], [&x, &y, &z], [p[0], p[1], p[2]]);
+        let expected = [
+            expected[0] * prefactor,
+            expected[1] * prefactor,
+            expected[2] * prefactor,
+        ];
+        let scale = expected.iter().fold(0.0, |max, b| f64::max(max, b.abs()));
+        assert_matches(found, expected, scale);
+    }
+}
+
+#[test]
+fn no_points_give_no_field() {
+    let (x, y, z) = grid();
+    let (jx, jy, jz) = current_density((x.len(), y.len(), z.len()));
+    let solver = BiotSavart::new(jx.view(), jy.view(), jz.view(), &x, &y, &z).unwrap();
+    let b = solver.field_at(Array2::zeros((0, 3)).view()).unwrap();
+    assert_eq!(b.dim(), (0, 3));
+}
+
+#[test]
+fn rejects_invalid_points() {
+    let (x, y, z) = grid();
+    let (jx, jy, jz) = current_density((x.len(), y.len(), z.len()));
+    let solver = BiotSavart::new(jx.view(), jy.view(), jz.view(), &x, &y, &z).unwrap();
+
+    assert_eq!(
+        solver.field_at(Array2::zeros((4, 2)).view()).unwrap_err(),
+        Error::ShapeMismatch {
+            name: "points",
+            expected: vec![3],
+            found: vec![2],
+        }
+    );
+    let points = array![[0.0, 0.0, 0.0], [0.1, std::f64::NAN, 0.2]];
+    assert_eq!(
+        solver.field_at(points.view()).unwrap_err(),
+        Error::NonFinite("points")
+    );
+}
@@ -0,0 +1,52 @@
+extern crate biot_savart;
+extern crate ndarray;
+
+mod common;
+
+use std::sync::Mutex;
+
+use biot_savart::{BiotSavart, Solver};
+
+use common::current_density;
+
+#[test]
+fn reports_every_slab() {
+    let x: Vec<f64> = (0..5).map(|i| i as f64 * 0.5).collect();
+    let (jx, jy, jz) = current_density((x.len(), x.len(), x.len()));
+    let target_x = [0.1, 0.2, 0.3, 0.4, 0.5, 0.6, 0.7];
+
+    for solver in [Solver::Direct, Solver::Tree(0.5)].iter() {
+        let reports = Mutex::new(Vec::new());
+        let progress = |done: usize, total: usize| reports.lock().unwrap().push((done, total));
+        BiotSavart::new(jx.view(), jy.view(), jz.view(), &x, &x, &x)
+            .unwrap()
+            .solver(*solver)
+            .progress(&progress)
+            .field_on_grid(&target_x, &x, &x[1..3])
+            .unwrap();
+
+        let mut reports = reports.into_inner().unwrap();
+        assert_eq!(reports[0], (0, target_x.len()));
+        reports.sort();
+        let expected: Vec<_> = (0..=target_x.len()).map(|n| (n, target_x.len())).collect();
+        assert_eq!(reports, expected);
+    }
+}
+
+#[test]
+fn fft_reports_every_stage() {
+    let x: Vec<f64> = (0..6).map(|i| i as f64 * 0.5).collect();
+    let (jx, jy, jz) = current_density((x.len(), x.len(), x.len()));
+
+    let reports = Mutex::new(Vec::new());
+    let progress = |done: usize, total: usize| reports.lock().unwrap().push((done, total));
+    BiotSavart::new(jx.view(), jy.view(), jz.view(), &x, &x, &x)
+        .unwrap()
+        .solver(Solver::Fft)
+        .progress(&progress)
+        .field()
+        .unwrap();
+
+    let expected: Vec<_> = (0..=4).map(|n| (n, 4)).collect();
+    assert_eq!(reports.into_inner().unwrap(), expected);
+}
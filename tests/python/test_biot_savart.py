@@ -0,0 +1,185 @@
+"""Smoke tests of the Python module
+
+Build the module and put it next to this file to run them:
+
+    cargo build --release --features extension-module
+    cp target/release/libbiot_savart.so tests/python/
+    pytest tests/python
+"""
+import _thread
+import logging
+
+import numpy as np
+import pytest
+
+bs = pytest.importorskip("libbiot_savart")
+
+
+def grid(n=7):
+    x = np.linspace(-1.0, 1.0, n)
+    return x, x.copy(), x.copy()
+
+
+def current_loop(x, y, z):
+    """J circulating counterclockwise around z"""
+    X, Y, Z = np.meshgrid(x, y, z, indexing="ij")
+    envelope = np.exp(-(X ** 2 + Y ** 2 + Z ** 2))
+    return -Y * envelope, X * envelope, np.zeros_like(X)
+
+
+def test_biot_returns_arrays():
+    x, y, z = grid()
+    jx, jy, jz = current_loop(x, y, z)
+    bx, by, bz, m = bs.biot(np.zeros(3), jx, jy, jz, x, y, z)
+    for b in (bx, by, bz):
+        assert isinstance(b, np.ndarray)
+        assert b.dtype == np.float64
+        assert b.shape == jx.shape
+    assert m.shape == (3,)
+    # B at the center of the loop and m point along z
+    assert bz[3, 3, 3] > 0
+    assert m[2] > 0
+
+
+def test_fortran_order_gives_the_same_field():
+    x, y, z = grid()
+    j = current_loop(x, y, z)
+    c_order = bs.biot(np.zeros(3), *j, x, y, z)
+    j = [np.asfortranarray(a) for a in j]
+    f_order = bs.biot(np.zeros(3), *j, x, y, z)
+    for a, b in zip(c_order, f_order):
+        np.testing.assert_allclose(a, b, rtol=1e-12, atol=0.0)
+
+
+def test_rejects_non_contiguous_j():
+    x = np.linspace(-1.0, 1.0, 12)
+    j = [a[::2, ::2, ::2] for a in current_loop(x, x, x)]
+    x = x[::2].copy()
+    with pytest.raises(ValueError, match="contiguous"):
+        bs.biot(np.zeros(3), *j, x, x, x)
+
+
+def test_biot_points_returns_nx3_array():
+    x, y, z = grid()
+    jx, jy, jz = current_loop(x, y, z)
+    points = np.array([[0.0, 0.0, 0.0], [0.3, -0.2, 0.5]])
+    b = bs.biot_points(points, jx, jy, jz, x, y, z)
+    assert isinstance(b.values, np.ndarray)
+    assert b.values.shape == (2, 3)
+
+
+def test_errors_derive_from_biot_savart_error():
+    assert issubclass(bs.BiotSavartError, ValueError)
+    for error in (
+        bs.ShapeMismatchError,
+        bs.EmptyGridError,
+        bs.NonMonotonicError,
+        bs.NonFiniteError,
+        bs.NonUniformGridError,
+        bs.UnsupportedBackendError,
+        bs.InvalidFileError,
+    ):
+        assert issubclass(error, bs.BiotSavartError)
+    assert issubclass(bs.CancelledError, RuntimeError)
+    assert not issubclass(bs.CancelledError, bs.BiotSavartError)
+
+
+def test_invalid_input_raises():
+    x, y, z = grid()
+    jx, jy, jz = current_loop(x, y, z)
+    center = np.zeros(3)
+    with pytest.raises(bs.ShapeMismatchError):
+        bs.biot(center, jx[:-1], jy, jz, x, y, z)
+    with pytest.raises(bs.NonFiniteError):
+        bs.biot(center, np.full_like(jx, np.nan), jy, jz, x, y, z)
+    unsorted = x.copy()
+    unsorted[[1, 2]] = unsorted[[2, 1]]
+    with pytest.raises(bs.NonMonotonicError):
+        bs.biot(center, jx, jy, jz, unsorted, y, z)
+    uneven = x.copy()
+    uneven[1] += 0.05
+    with pytest.raises(bs.NonUniformGridError):
+        bs.biot(center, jx, jy, jz, uneven, y, z, solver="fft")
+    with pytest.raises(ValueError):
+        bs.biot(center, jx, jy, jz, x, y, z, solver="magic")
+
+
+def test_missing_file_raises_os_error(tmp_path):
+    missing = str(tmp_path / "missing.cube")
+    with pytest.raises(OSError):
+        bs.biot_cube(np.zeros(3), missing, missing, missing)
+
+
+def large_loop():
+    """Grid large enough that the direct sum takes seconds"""
+    x, y, z = grid(32)
+    return (x, y, z), current_loop(x, y, z)
+
+
+def test_cancel_token_raises_cancelled_error():
+    (x, y, z), j = large_loop()
+    token = bs.CancelToken()
+    assert not token.cancelled
+    # cancels once the calculation is running
+    with pytest.raises(bs.CancelledError):
+        bs.biot(
+            np.zeros(3),
+            *j,
+            x,
+            y,
+            z,
+            cancel=token,
+            progress=lambda done, total: token.cancel()
+        )
+    assert token.cancelled
+
+
+def test_ctrl_c_raises_keyboard_interrupt():
+    (x, y, z), j = large_loop()
+    with pytest.raises(KeyboardInterrupt):
+        bs.biot(
+            np.zeros(3),
+            *j,
+            x,
+            y,
+            z,
+            progress=lambda done, total: _thread.interrupt_main()
+        )
+
+
+def test_exception_in_progress_is_raised():
+    (x, y, z), j = large_loop()
+
+    def progress(done, total):
+        raise ZeroDivisionError
+
+    with pytest.raises(ZeroDivisionError):
+        bs.biot(np.zeros(3), *j, x, y, z, progress=progress)
+
+
+def test_progress_reaches_total():
+    x, y, z = grid()
+    j = current_loop(x, y, z)
+    reports = []
+    bs.biot(
+        np.zeros(3),
+        *j,
+        x,
+        y,
+        z,
+        progress=lambda done, total: reports.append((done, total))
+    )
+    done = [done for done, _ in reports]
+    assert done == sorted(done)
+    assert reports[-1][0] == reports[-1][1] > 0
+
+
+def test_log_records_reach_logging(caplog):
+    x, y, z = grid()
+    j = current_loop(x, y, z)
+    with caplog.at_level(logging.INFO, logger="biot_savart"):
+        bs.biot(np.zeros(3), *j, x, y, z)
+    assert any(
+        record.name.startswith("biot_savart.") and "calculating B" in record.message
+        for record in caplog.records
+    )
@@ -0,0 +1,117 @@
+extern crate biot_savart;
+extern crate ndarray;
+
+mod common;
+
+use biot_savart::{BiotSavart, Error, Shielding, ShieldingTensor};
+use ndarray::prelude::*;
+
+use common::close;
+
+// Diamagnetic ring current in the xy plane, circulating clockwise seen from +z
+// as induced by a field along z
+fn ring_current(cor: &[f64]) -> (Array3<f64>, Array3<f64>, Array3<f64>) {
+    let n = cor.len();
+    let weight = |x: f64, y: f64, z: f64| {
+        let rho = (x * x + y * y).sqrt();
+        (-(rho - 1.5).powi(2) * 4.0 - z * z * 4.0).exp()
+    };
+    let jx = Array3::from_shape_fn((n, n, n), |(i, j, k)| {
+        cor[j] * weight(cor[i], cor[j], cor[k])
+    });
+    let jy = Array3::from_shape_fn((n, n, n), |(i, j, k)| {
+        -cor[i] * weight(cor[i], cor[j], cor[k])
+    });
+    (jx, jy, Array3::zeros((n, n, n)))
+}
+
+#[test]
+fn tensor_columns_are_the_induced_fields() {
+    let cor: Vec<f64> = (0..15).map(|i| -3.5 + i as f64 * 0.5).collect();
+    let (jx, jy, jz) = ring_current(&cor);
+    let zero = Array3::zeros(jx.dim());
+    let points = arr2(&[[0.0, 0.0, 0.0], [0.1, 0.2, 1.0]]);
+
+    let solver = |x, y, z| BiotSavart::new(x, y, z, &cor, &cor, &cor).unwrap();
+    let shielding = Shielding::new(
+        solver(zero.view(), zero.view(), zero.view()),
+        solver(jy.view(), jz.view(), jx.view()),
+        solver(jx.view(), jy.view(), jz.view()),
+    );
+    let tensors = shielding.at(points.view()).unwrap();
+    let b_y = solver(jy.view(), jz.view(), jx.view())
+        .field_at(points.view())
+        .unwrap();
+    let b_z = solver(jx.view(), jy.view(), jz.view())
+        .field_at(points.view())
+        .unwrap();
+
+    assert_eq!(tensors.len(), 2);
+    for (n, tensor) in tensors.iter().enumerate() {
+        for (a, row) in tensor.tensor.iter().enumerate() {
+            assert_eq!(row[0], 0.0);
+            assert!(close(row[1], -1e6 * b_y[[n, a]]));
+            assert!(close(row[2], -1e6 * b_z[[n, a]]));
+        }
+    }
+    // the diamagnetic current shields the center
+    assert!(tensors[0].zz() > 0.0);
+    assert!(close(tensors[0].zz(), tensors[0].tensor[2][2]));
+}
+
+#[test]
+fn isotropic_and_anisotropy() {
+    // symmetric part with principal components 1, 3 and 5, plus an
+    // antisymmetric part
+    let tensor = ShieldingTensor {
+        tensor: [[2.0, 1.5, 0.0], [0.5, 2.0, -1.0], [0.0, 1.0, 5.0]],
+    };
+    let principal = tensor.principal();
+
+    assert!(close(tensor.isotropic(), 3.0));
+    for (found, expected) in principal.iter().zip(&[1.0, 3.0, 5.0]) {
+        assert!(close(*found, *expected));
+    }
+    assert!(close(tensor.anisotropy(), 3.0));
+    assert_eq!(tensor.zz(), 5.0);
+}
+
+#[test]
+fn line_and_plane_match_points() {
+    let cor: Vec<f64> = (0..11).map(|i| -2.5 + i as f64 * 0.5).collect();
+    let (jx, jy, jz) = ring_current(&cor);
+    let solver = |x, y, z| BiotSavart::new(x, y, z, &cor, &cor, &cor).unwrap();
+    let shielding = Shielding::new(
+        solver(jz.view(), jx.view(), jy.view()),
+        solver(jy.view(), jz.view(), jx.view()),
+        solver(jx.view(), jy.view(), jz.view()),
+    );
+
+    let line = shielding.line([0.1, 0.0, 0.0], [0.1, 0.0, 3.0], 4).unwrap();
+    let points = shielding
+        .at(arr2(&[[0.1, 0.0, 1.0], [0.1, 0.0, 3.0]]).view())
+        .unwrap();
+    assert_eq!(line.distances, vec![0.0, 1.0, 2.0, 3.0]);
+    assert_eq!(line.points.row(2).to_vec(), vec![0.1, 0.0, 2.0]);
+    assert_eq!(line.tensors[1], points[0]);
+    assert_eq!(line.tensors[3], points[1]);
+
+    let plane = shielding
+        .plane([-1.0, -1.0, 1.0], [2.0, 0.0, 0.0], [0.0, 3.0, 0.0], 3, 4)
+        .unwrap();
+    let points = shielding
+        .at(arr2(&[[0.0, 1.0, 1.0], [1.0, -1.0, 1.0]]).view())
+        .unwrap();
+    assert_eq!(plane.u, vec![0.0, 1.0, 2.0]);
+    assert_eq!(plane.v, vec![0.0, 1.0, 2.0, 3.0]);
+    assert_eq!(plane.points.dim(), (3, 4, 3));
+    assert_eq!(plane.tensors[[1, 2]], points[0]);
+    assert_eq!(plane.tensors[[2, 0]], points[1]);
+    let nics = plane.tensors.map(|tensor| -tensor.isotropic());
+    assert_eq!(nics.dim(), (3, 4));
+
+    assert_eq!(
+        shielding.line([0.0; 3], [1.0; 3], 0).unwrap_err(),
+        Error::EmptyGrid("line")
+    );
+}
@@ -0,0 +1,208 @@
+extern crate biot_savart;
+extern crate ndarray;
+
+use biot_savart::{BiotSavart, Singularity, Solver, Units};
+use ndarray::prelude::*;
+
+// Gradient of the linear current density, G[a][b] = ∂J_a/∂x_b
+const GRADIENT: [[f64; 3]; 3] = [[2.0, -1.0, 0.5], [0.3, 1.5, -2.0], [-1.0, 0.7, 1.0]];
+// Current density at the origin
+const OFFSET: [f64; 3] = [1.0, -1.0, 0.5];
+
+fn linear_current(x: &[f64], y: &[f64], z: &[f64]) -> (Array3<f64>, Array3<f64>, Array3<f64>) {
+    let shape = (x.len(), y.len(), z.len());
+    let component = |a: usize| {
+        Array3::from_shape_fn(shape, |(i, j, k)| {
+            OFFSET[a] + GRADIENT[a][0] * x[i] + GRADIENT[a][1] * y[j] + GRADIENT[a][2] * z[k]
+        })
+    };
+    (component(0), component(1), component(2))
+}
+
+fn coordinates(n: usize, h: f64) -> Vec<f64> {
+    (0..n).map(|i| (i as f64 - 2.0) * h).collect()
+}
+
+fn cross(a: &[f64; 3], b: &[f64; 3]) -> [f64; 3] {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
+
+fn max_abs_diff(a: &Array3<f64>, b: &Array3<f64>) -> f64 {
+    a.iter()
+        .zip(b.iter())
+        .fold(0.0, |max, (a, b)| f64::max(max, (a - b).abs()))
+}
+
+#[test]
+fn analytic_voxel_matches_numerical_integral() {
+    let h = [0.5, 0.4, 0.3];
+    let (x, y, z) = (
+        coordinates(5, h[0]),
+        coordinates(6, h[1]),
+        coordinates(5, h[2]),
+    );
+    let (jx, jy, jz) = linear_current(&x, &y, &z);
+    let solver = |singularity| {
+        BiotSavart::new(jx.view(), jy.view(), jz.view(), &x, &y, &z)
+            .unwrap()
+            .units(Units::SI)
+            .singularity(singularity)
+    };
+
+    // the target is the grid point at the origin, so the voxel is the only
+    // difference between the two
+    let target = array![[0.0, 0.0, 0.0]];
+    let analytic = solver(Singularity::Analytic)
+        .field_at(target.view())
+        .unwrap();
+    let exclude = solver(Singularity::Exclude)
+        .field_at(target.view())
+        .unwrap();
+    let voxel = &analytic - &exclude;
+
+    // Midpoint rule over n³ subcells of the voxel, leaving out the one at the
+    // singularity. J(s) × -s / |s|³ is odd in s apart from the part linear in
+    // J, which scales as 1/|s|, so the subcell left out holds 1/n² of it.
+    let n = 41;
+    let sub = [h[0] / n as f64, h[1] / n as f64, h[2] / n as f64];
+    let dv = sub[0] * sub[1] * sub[2];
+    let mut sum = [0f64; 3];
+    let half = (n / 2) as f64;
+    for i in 0..n {
+        for j in 0..n {
+            for k in 0..n {
+                if (i, j, k) == (n / 2, n / 2, n / 2) {
+                    continue;
+                }
+                let s = [
+                    (i as f64 - half) * sub[0],
+                    (j as f64 - half) * sub[1],
+                    (k as f64 - half) * sub[2],
+                ];
+                let mut current = OFFSET;
+                for (a, current) in current.iter_mut().enumerate() {
+                    *current += (0..3).map(|b| GRADIENT[a][b] * s[b]).sum::<f64>();
+                }
+                let r3 = (s[0] * s[0] + s[1] * s[1] + s[2] * s[2]).powf(1.5);
+                let b = cross(&current, &[-s[0], -s[1], -s[2]]);
+                for (sum, b) in sum.iter_mut().zip(&b) {
+                    *sum += b / r3 * dv;
+                }
+            }
+        }
+    }
+    let prefactor = Units::SI.field_prefactor() / (1.0 - 1.0 / (n * n) as f64);
+    let reference: Vec<f64> = sum.iter().map(|b| b * prefactor).collect();
+
+    let scale = reference.iter().fold(0.0, |max, b| f64::max(max, b.abs()));
+    for (voxel, reference) in voxel.iter().zip(&reference) {
+        assert!(
+            (voxel - reference).abs() < 2e-3 * scale,
+            "{} != {}",
+            voxel,
+            reference
+        );
+    }
+}
+
+#[test]
+fn soften_tends_to_exclude() {
+    let x = coordinates(6, 0.5);
+    let y = coordinates(5, 0.4);
+    let z = coordinates(7, 0.3);
+    let (jx, jy, jz) = linear_current(&x, &y, &z);
+    let field = |singularity| {
+        BiotSavart::new(jx.view(), jy.view(), jz.view(), &x, &y, &z)
+            .unwrap()
+            .singularity(singularity)
+            .field()
+            .unwrap()
+    };
+
+    let exclude = field(Singularity::Exclude);
+    let scale = exclude.0.iter().fold(0.0, |max, b| f64::max(max, b.abs()));
+    let mut previous = std::f64::INFINITY;
+    for &eps in &[0.1, 0.01, 0.001] {
+        let soften = field(Singularity::Soften(eps));
+        let diff = max_abs_diff(&soften.0, &exclude.0)
+            .max(max_abs_diff(&soften.1, &exclude.1))
+            .max(max_abs_diff(&soften.2, &exclude.2));
+        // the kernel differs by O(ε²) away from the target
+        assert!(diff < 0.05 * previous);
+        previous = diff;
+    }
+    assert!(previous < 1e-5 * scale);
+}
+
+#[test]
+fn fft_matches_direct_with_analytic_voxel() {
+    let x = coordinates(7, 0.5);
+    let y = coordinates(6, 0.4);
+    let z = coordinates(8, 0.3);
+    let (jx, jy, jz) = linear_current(&x, &y, &z);
+    let field = |solver| {
+        BiotSavart::new(jx.view(), jy.view(), jz.view(), &x, &y, &z)
+            .unwrap()
+            .solver(solver)
+            .singularity(Singularity::Analytic)
+            .field()
+            .unwrap()
+    };
+
+    let direct = field(Solver::Direct);
+    let fft = field(Solver::Fft);
+    let scale = direct.0.iter().fold(0.0, |max, b| f64::max(max, b.abs()));
+    assert!(max_abs_diff(&direct.0, &fft.0) < 1e-10 * scale);
+    assert!(max_abs_diff(&direct.1, &fft.1) < 1e-10 * scale);
+    assert!(max_abs_diff(&direct.2, &fft.2) < 1e-10 * scale);
+
+    // the voxels make a difference
+    let exclude = BiotSavart::new(jx.view(), jy.view(), jz.view(), &x, &y, &z)
+        .unwrap()
+        .solver(Solver::Fft)
+        .field()
+        .unwrap();
+    assert!(max_abs_diff(&exclude.0, &fft.0) > 1e-3 * scale);
+}
+
+#[test]
+fn parses_singularities() {
+    assert_eq!("exclude".parse(), Ok(Singularity::Exclude));
+    assert_eq!("Analytic".parse(), Ok(Singularity::Analytic));
+    assert_eq!("soften:0.25".parse(), Ok(Singularity::Soften(0.25)));
+    // without a length, softening would do nothing
+    for s in &[
+        "soften",
+        "soften:0",
+        "soften:-1",
+        "soften:inf",
+        "soften:x",
+        "exclude:1",
+    ] {
+        assert!(s.parse::<Singularity>().is_err(), "{}", s);
+    }
+}
+
+#[test]
+fn parses_singularities_with_softening() {
+    assert_eq!(
+        Singularity::parse("soften", Some(0.5)),
+        Ok(Singularity::Soften(0.5))
+    );
+    // a length after the colon wins over the separate one
+    assert_eq!(
+        Singularity::parse("soften:0.25", Some(0.5)),
+        Ok(Singularity::Soften(0.25))
+    );
+    assert_eq!(
+        Singularity::parse("analytic", Some(0.5)),
+        Ok(Singularity::Analytic)
+    );
+    for softening in &[None, Some(0.0), Some(-1.0), Some(std::f64::NAN)] {
+        assert!(Singularity::parse("soften", *softening).is_err());
+    }
+}
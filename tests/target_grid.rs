@@ -0,0 +1,112 @@
+extern crate biot_savart;
+extern crate ndarray;
+
+mod common;
+
+use biot_savart::{BiotSavart, Error, Units};
+use ndarray::prelude::*;
+
+use common::{current_density, direct_field};
+
+// Grid with uneven spacing and a decreasing dimension
+fn grid() -> (Vec<f64>, Vec<f64>, Vec<f64>) {
+    let x = vec![-1.0, -0.4, 0.0, 0.3, 1.1];
+    let y = vec![0.9, 0.5, 0.2, -0.5];
+    let z = vec![-0.6, -0.1, 0.25, 0.5, 1.0, 1.2];
+    (x, y, z)
+}
+
+// Checks B on the grid of `target` against the direct sum over the grid of J
+// at each of its points.
+fn check(
+    b: &(Array3<f64>, Array3<f64>, Array3<f64>),
+    j: [&Array3<f64>; 3],
+    cor: [&[f64]; 3],
+    target: [&[f64]; 3],
+) {
+    assert_eq!(
+        b.0.dim(),
+        (target[0].len(), target[1].len(), target[2].len())
+    );
+    let prefactor = Units::Atomic.field_prefactor();
+    let expected = Array3::from_shape_fn(b.0.dim(), |(i, k, l)| {
+        direct_field(j, cor, [target[0][i], target[1][k], target[2][l]])
+    });
+    let scale = expected
+        .iter()
+        .flat_map(|b| b.iter())
+        .fold(0.0, |max, b| f64::max(max, b.abs()))
+        * prefactor;
+
+    for (idx, expected) in expected.indexed_iter() {
+        let found = [b.0[idx], b.1[idx], b.2[idx]];
+        for (found, expected) in found.iter().zip(expected) {
+            assert!(
+                (found - expected * prefactor).abs() <= 1e-12 * scale,
+                "{:?}: {} != {}",
+                idx,
+                found,
+                expected * prefactor
+            );
+        }
+    }
+}
+
+#[test]
+fn grid_of_j_matches_direct_sum() {
+    let (x, y, z) = grid();
+    let (jx, jy, jz) = current_density((x.len(), y.len(), z.len()));
+    let solver = BiotSavart::new(jx.view(), jy.view(), jz.view(), &x, &y, &z).unwrap();
+
+    let b = solver.field_on_grid(&x, &y, &z).unwrap();
+    check(&b, [&jx, &jy, &jz], [&x, &y, &z], [&x, &y, &z]);
+    assert_eq!(b, solver.field().unwrap());
+}
+
+#[test]
+fn finer_and_larger_grid_matches_direct_sum() {
+    let (x, y, z) = grid();
+    let (jx, jy, jz) = current_density((x.len(), y.len(), z.len()));
+    let solver = BiotSavart::new(jx.view(), jy.view(), jz.view(), &x, &y, &z).unwrap();
+
+    // twice as fine as J along x, reaching past it along y, and partly on the
+    // grid of J along z
+    let target_x: Vec<f64> = (0..9).map(|i| -1.0 + i as f64 * 0.25).collect();
+    let target_y = [-2.0, -0.5, 0.35, 1.5];
+    let target_z = [0.25, 0.4, 1.2];
+    let b = solver
+        .field_on_grid(&target_x, &target_y, &target_z)
+        .unwrap();
+    check(
+        &b,
+        [&jx, &jy, &jz],
+        [&x, &y, &z],
+        [&target_x, &target_y, &target_z],
+    );
+}
+
+#[test]
+fn plane_and_line_match_direct_sum() {
+    let (x, y, z) = grid();
+    let (jx, jy, jz) = current_density((x.len(), y.len(), z.len()));
+    let solver = BiotSavart::new(jx.view(), jy.view(), jz.view(), &x, &y, &z).unwrap();
+
+    let plane = solver.field_on_grid(&x, &y, &[0.4]).unwrap();
+    check(&plane, [&jx, &jy, &jz], [&x, &y, &z], [&x, &y, &[0.4]]);
+    let line = solver.field_on_grid(&[0.2], &[-0.1], &z).unwrap();
+    check(&line, [&jx, &jy, &jz], [&x, &y, &z], [&[0.2], &[-0.1], &z]);
+}
+
+#[test]
+fn rejects_non_finite_targets() {
+    let (x, y, z) = grid();
+    let (jx, jy, jz) = current_density((x.len(), y.len(), z.len()));
+    let solver = BiotSavart::new(jx.view(), jy.view(), jz.view(), &x, &y, &z).unwrap();
+
+    assert_eq!(
+        solver
+            .field_on_grid(&x, &[0.0, std::f64::INFINITY], &z)
+            .unwrap_err(),
+        Error::NonFinite("target_y")
+    );
+}
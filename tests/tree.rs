@@ -0,0 +1,130 @@
+extern crate biot_savart;
+extern crate ndarray;
+
+mod common;
+
+use biot_savart::{BiotSavart, Error, Solver};
+use ndarray::prelude::*;
+
+use common::current_density;
+
+// Returns the largest |B - B_direct| and the largest |B_direct| over the grid
+fn max_errors(
+    b: &(Array3<f64>, Array3<f64>, Array3<f64>),
+    direct: &(Array3<f64>, Array3<f64>, Array3<f64>),
+) -> (f64, f64) {
+    let mut max_abs = 0f64;
+    let mut max_direct = 0f64;
+    for (idx, bx) in b.0.indexed_iter() {
+        let error = [
+            bx - direct.0[idx],
+            b.1[idx] - direct.1[idx],
+            b.2[idx] - direct.2[idx],
+        ];
+        let error = error.iter().map(|e| e * e).sum::<f64>().sqrt();
+        let norm = [direct.0[idx], direct.1[idx], direct.2[idx]]
+            .iter()
+            .map(|b| b * b)
+            .sum::<f64>()
+            .sqrt();
+        max_abs = max_abs.max(error);
+        max_direct = max_direct.max(norm);
+    }
+    (max_abs, max_direct)
+}
+
+#[test]
+fn tree_converges_to_direct() {
+    let x: Vec<f64> = (0..9).map(|i| i as f64 * 0.4 - 1.0).collect();
+    let y: Vec<f64> = (0..9).map(|i| i as f64 * 0.3 - 1.2).collect();
+    let z: Vec<f64> = (0..8).map(|i| 2.0 - i as f64 * 0.5).collect();
+    let (jx, jy, jz) = current_density((x.len(), y.len(), z.len()));
+    let total = x.len() * y.len() * z.len();
+
+    let solver = || BiotSavart::new(jx.view(), jy.view(), jz.view(), &x, &y, &z).unwrap();
+    let direct = solver().field().unwrap();
+
+    let mut previous = std::f64::INFINITY;
+    for &theta in &[0.4, 0.2, 0.1] {
+        let tree = solver().solver(Solver::Tree(theta));
+        let (max_abs, max_direct) = max_errors(&tree.field().unwrap(), &direct);
+        let max_rel = max_abs / max_direct;
+        // the expansion is first order, so the error goes with theta²
+        assert!(
+            max_rel < 2.0 * theta * theta,
+            "{} at theta {}",
+            max_rel,
+            theta
+        );
+        assert!(max_rel < 0.5 * previous);
+        previous = max_rel;
+
+        // sampling every grid point finds the same error
+        let estimate = tree.estimate_error(total).unwrap();
+        assert!((estimate.max_abs - max_abs).abs() <= 1e-12 * max_direct);
+        assert!((estimate.max_rel - max_rel).abs() <= 1e-12);
+    }
+}
+
+#[test]
+fn tree_with_zero_theta_is_direct() {
+    let x: Vec<f64> = (0..9).map(|i| i as f64 * 0.4 - 1.0).collect();
+    let y: Vec<f64> = (0..6).map(|i| i as f64 * 0.3 - 1.2).collect();
+    let z = vec![0.0, 0.5, 1.5, 2.0, 3.0];
+    let (jx, jy, jz) = current_density((x.len(), y.len(), z.len()));
+
+    let solver = BiotSavart::new(jx.view(), jy.view(), jz.view(), &x, &y, &z).unwrap();
+    let direct = solver.field().unwrap();
+    let tree = solver.solver(Solver::Tree(0.0));
+    let (max_abs, max_direct) = max_errors(&tree.field().unwrap(), &direct);
+    // only the order of the additions differs
+    assert!(max_abs <= 1e-12 * max_direct);
+
+    let estimate = tree.estimate_error(50).unwrap();
+    assert!(estimate.max_rel < 1e-12);
+    assert!(estimate.rms_rel < 1e-12);
+}
+
+#[test]
+fn tree_rejects_negative_or_nan_theta() {
+    let x = vec![0.0, 1.0, 2.0];
+    let (jx, jy, jz) = current_density((x.len(), x.len(), x.len()));
+    for &theta in &[-0.1, std::f64::NAN] {
+        let tree = BiotSavart::new(jx.view(), jy.view(), jz.view(), &x, &x, &x)
+            .unwrap()
+            .solver(Solver::Tree(theta));
+        match tree.field().unwrap_err() {
+            Error::InvalidTheta(found) => assert!(found.is_nan() || found == theta),
+            err => panic!("unexpected error {:?}", err),
+        }
+        match tree.field_at(array![[0.5, 0.5, 0.5]].view()).unwrap_err() {
+            Error::InvalidTheta(_) => {}
+            err => panic!("unexpected error {:?}", err),
+        }
+    }
+}
+
+#[test]
+fn error_estimate_is_finite_without_field() {
+    let x = vec![0.0, 1.0, 2.0];
+    let (jx, jy, jz) = current_density((x.len(), x.len(), x.len()));
+    let zeros = Array3::zeros(jx.dim());
+
+    let solver = BiotSavart::new(jx.view(), jy.view(), jz.view(), &x, &x, &x)
+        .unwrap()
+        .solver(Solver::Tree(0.5));
+    let estimate = solver.estimate_error(0).unwrap();
+    assert_eq!(
+        (estimate.max_abs, estimate.max_rel, estimate.rms_rel),
+        (0.0, 0.0, 0.0)
+    );
+
+    let solver = BiotSavart::new(zeros.view(), zeros.view(), zeros.view(), &x, &x, &x)
+        .unwrap()
+        .solver(Solver::Tree(0.5));
+    let estimate = solver.estimate_error(10).unwrap();
+    assert_eq!(
+        (estimate.max_abs, estimate.max_rel, estimate.rms_rel),
+        (0.0, 0.0, 0.0)
+    );
+}
@@ -0,0 +1,127 @@
+extern crate biot_savart;
+extern crate ndarray;
+
+mod common;
+
+use biot_savart::{BiotSavart, Units};
+use ndarray::prelude::*;
+
+use common::close;
+
+// Speed of light in cm/s
+const C: f64 = 2.997_924_58e10;
+
+#[test]
+fn si_prefactors() {
+    // μ0/4π
+    assert!(close(Units::SI.field_prefactor(), 1e-7));
+    assert!(close(Units::SI.magnetization_prefactor(), 0.5));
+    // A/T to nA/T
+    assert!(close(Units::SI.current_strength_prefactor(), 1e9));
+}
+
+#[test]
+fn gaussian_prefactors() {
+    assert!(close(Units::Gaussian.field_prefactor(), 1.0 / C));
+    assert!(close(Units::Gaussian.magnetization_prefactor(), 0.5 / C));
+    // 1 statA/G = (10 / c) A / 1e-4 T
+    let statampere = 10.0 / C;
+    assert!(close(
+        Units::Gaussian.current_strength_prefactor(),
+        statampere / 1e-4 * 1e9
+    ));
+}
+
+#[test]
+fn atomic_prefactors() {
+    // α² from the inverse fine-structure constant, CODATA 2018
+    let alpha = 1.0 / 137.035_999_084;
+    assert!((Units::Atomic.field_prefactor() / (alpha * alpha) - 1.0).abs() < 1e-9);
+    assert!(close(Units::Atomic.magnetization_prefactor(), 0.5));
+
+    // e² E_h a0² / ħ² in A/T from the SI values, CODATA 2018
+    let charge = 1.602_176_634e-19;
+    let hartree = 4.359_744_722_207_1e-18;
+    let bohr = 5.291_772_109_03e-11;
+    let hbar = 1.054_571_817e-34;
+    let current = charge * charge * hartree * bohr * bohr / (hbar * hbar);
+    assert!((Units::Atomic.current_strength_prefactor() / (current * 1e9) - 1.0).abs() < 1e-9);
+}
+
+#[test]
+fn results_scale_with_the_prefactors() {
+    let cor = [-1.0, 0.0, 1.0];
+    let jx = Array3::from_shape_fn((3, 3, 3), |(_, j, _)| -cor[j]);
+    let jy = Array3::from_shape_fn((3, 3, 3), |(i, _, _)| cor[i]);
+    let jz = Array3::from_shape_fn((3, 3, 3), |(i, j, k)| 0.1 * cor[i] * cor[j] * cor[k]);
+    let points = array![[0.3, -0.2, 2.5], [1.5, 1.5, -1.5]];
+
+    let solver = |units| {
+        BiotSavart::new(jx.view(), jy.view(), jz.view(), &cor, &cor, &cor)
+            .unwrap()
+            .units(units)
+    };
+    let si = solver(Units::SI);
+    let b_si = si.field_at(points.view()).unwrap();
+    let m_si = si.magnetization([0.2, 0.0, 0.0]);
+    for &units in &[Units::Gaussian, Units::Atomic] {
+        let other = solver(units);
+        let field_ratio = units.field_prefactor() / Units::SI.field_prefactor();
+        let b = other.field_at(points.view()).unwrap();
+        for (&b, &b_si) in b.iter().zip(b_si.iter()) {
+            assert!(close(b, field_ratio * b_si));
+        }
+
+        let magnetization_ratio =
+            units.magnetization_prefactor() / Units::SI.magnetization_prefactor();
+        let m = other.magnetization([0.2, 0.0, 0.0]);
+        for (&m, &m_si) in m.iter().zip(m_si.iter()) {
+            assert!(close(m, magnetization_ratio * m_si));
+        }
+    }
+}
+
+#[test]
+fn cells_are_weighted_by_their_width() {
+    // the cells reach halfway to the neighbouring points, and the outermost ones
+    // as far outwards as inwards, so the widths are
+    // x: 1, 1.5, 3, 4; y: 2, 2; z: 0.5, 0.75, 1
+    let x_cor = [0.0, 1.0, 3.0, 7.0];
+    let y_cor = [-1.0, 1.0];
+    let z_cor = [0.0, 0.5, 1.5];
+    let shape = (x_cor.len(), y_cor.len(), z_cor.len());
+    let ones = Array3::from_elem(shape, 1.0);
+    let zeros = Array3::zeros(shape);
+
+    let solver = BiotSavart::new(
+        ones.view(),
+        zeros.view(),
+        zeros.view(),
+        &x_cor,
+        &y_cor,
+        &z_cor,
+    )
+    .unwrap()
+    .units(Units::SI);
+    let net_current = solver.multipoles([0.0; 3]).net_current;
+    assert!(close(net_current[0], 9.5 * 4.0 * 2.25));
+    assert!(close(net_current[1], 0.0));
+    assert!(close(net_current[2], 0.0));
+
+    // J along z, so m = (y, -x, 0) dV / 2 summed over the cells
+    let solver = BiotSavart::new(
+        zeros.view(),
+        zeros.view(),
+        ones.view(),
+        &x_cor,
+        &y_cor,
+        &z_cor,
+    )
+    .unwrap()
+    .units(Units::SI);
+    let m = solver.magnetization([0.0; 3]);
+    let x_moment = 1.0 * 1.5 + 3.0 * 3.0 + 7.0 * 4.0;
+    assert!(close(m[0], 0.0));
+    assert!(close(m[1], -0.5 * x_moment * 4.0 * 2.25));
+    assert!(close(m[2], 0.0));
+}